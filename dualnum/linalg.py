@@ -0,0 +1,11 @@
+"""Linear algebra for matrices of real and dual numbers."""
+from .dualnum import linalg as _linalg
+
+solve = _linalg.solve
+inv = _linalg.inv
+det = _linalg.det
+cholesky = _linalg.cholesky
+lu = _linalg.lu
+eigh = _linalg.eigh
+
+__all__ = ["solve", "inv", "det", "cholesky", "lu", "eigh"]
@@ -0,0 +1,8 @@
+"""Derivatives from dual numbers as callables for scipy.optimize."""
+from .dualnum import scipy as _scipy
+
+jac = _scipy.jac
+hess = _scipy.hess
+hessp = _scipy.hessp
+
+__all__ = ["jac", "hess", "hessp"]
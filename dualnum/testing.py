@@ -0,0 +1,7 @@
+"""Comparison of (hyper) dual numbers in tests."""
+from .dualnum import testing as _testing
+
+assert_allclose = _testing.assert_allclose
+isclose = _testing.isclose
+
+__all__ = ["assert_allclose", "isclose"]
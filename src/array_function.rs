@@ -0,0 +1,232 @@
+//! Numpy functions evaluated in dual arithmetic.
+//!
+//! Numpy does not dispatch functions to the elements of object arrays, so
+//! `np.interp` or `np.polyval` fail for plain arrays of dual numbers. The
+//! derive functions therefore return arrays of dual numbers as
+//! `DualObjectArray`, a subclass of `numpy.ndarray` that implements the
+//! `__array_function__` protocol in the same way as the scalar dual numbers.
+use crate::coerce::{dual_entry, extract_array, extract_real_vec, PyDual};
+use num_dual::DualNum;
+use pyo3::exceptions::PyValueError;
+use pyo3::once_cell::GILOnceCell;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple, PyType};
+use pyo3::wrap_pyfunction;
+
+static OBJECT_ARRAY: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+/// Create the `DualObjectArray` class and add it to the module.
+pub fn register_object_array(py: Python, m: &PyModule) -> PyResult<()> {
+    let ndarray = py.import("numpy")?.getattr("ndarray")?;
+    let dict = PyDict::new(py);
+    dict.set_item("__module__", "dualnum")?;
+    dict.set_item(
+        "__doc__",
+        "Numpy array of dual numbers that evaluates numpy functions in dual arithmetic.",
+    )?;
+    dict.set_item(
+        "__array_function__",
+        wrap_pyfunction!(object_array_function, m)?,
+    )?;
+    let cls: &PyType = py
+        .get_type::<PyType>()
+        .call1(("DualObjectArray", (ndarray,), dict))?
+        .downcast()?;
+    m.add("DualObjectArray", cls)?;
+    let _ = OBJECT_ARRAY.set(py, cls.into());
+    Ok(())
+}
+
+/// View an object array as `DualObjectArray`.
+pub fn object_array(py: Python, array: &PyAny) -> PyResult<PyObject> {
+    match OBJECT_ARRAY.get(py) {
+        Some(cls) => Ok(array.call_method1("view", (cls.as_ref(py),))?.into()),
+        None => Ok(array.into()),
+    }
+}
+
+/// `__array_function__` of `DualObjectArray`.
+///
+/// numpy looks the method up on the type and passes the array explicitly.
+#[pyfunction]
+fn object_array_function(
+    py: Python,
+    _array: &PyAny,
+    func: &PyAny,
+    _types: &PyAny,
+    args: &PyTuple,
+    kwargs: &PyDict,
+) -> PyResult<PyObject> {
+    let default = || -> PyResult<PyObject> {
+        Ok(func
+            .getattr("_implementation")?
+            .call(args, Some(kwargs))?
+            .into())
+    };
+    let args_vec: Vec<&PyAny> = args.iter().collect();
+    match dual_entry(&args_vec)? {
+        Some(x) => dispatch_dual!(x, array_function(py, func, args, kwargs), default()),
+        None => default(),
+    }
+}
+
+/// Dispatch a numpy function that is called with dual numbers.
+///
+/// `sum`, `prod`, `dot`, `interp`, `polyval` and `linalg.norm` are
+/// evaluated in dual arithmetic. All other functions (and calls with
+/// keyword arguments) are passed on to the default numpy implementation.
+pub fn array_function<P: PyDual>(
+    py: Python,
+    func: &PyAny,
+    args: &PyTuple,
+    kwargs: &PyDict,
+) -> PyResult<PyObject> {
+    let name: &str = func.getattr("__name__")?.extract()?;
+    let module: &str = func.getattr("__module__")?.extract()?;
+    if kwargs.is_empty() {
+        let res = match (module, name, args.len()) {
+            ("numpy", "sum", 1) => Some(sum_generic::<P>(py, args.get_item(0))?),
+            ("numpy", "prod", 1) => Some(prod_generic::<P>(py, args.get_item(0))?),
+            ("numpy", "dot", 2) => Some(dot_generic::<P>(py, args.get_item(0), args.get_item(1))?),
+            ("numpy", "interp", 3) => Some(interp_generic::<P>(
+                py,
+                args.get_item(0),
+                args.get_item(1),
+                args.get_item(2),
+            )?),
+            ("numpy", "polyval", 2) => Some(polyval_generic::<P>(
+                py,
+                args.get_item(0),
+                args.get_item(1),
+            )?),
+            ("numpy.linalg", "norm", 1) => Some(norm_generic::<P>(py, args.get_item(0))?),
+            _ => None,
+        };
+        if let Some(res) = res {
+            return Ok(res);
+        }
+    }
+    Ok(func
+        .getattr("_implementation")?
+        .call(args, Some(kwargs))?
+        .into())
+}
+
+/// A (dual) number for an empty shape, otherwise a numpy array.
+fn to_object<P: PyDual>(py: Python, shape: &[usize], x: Vec<P::Data>) -> PyResult<PyObject> {
+    if shape.is_empty() {
+        return Ok(P::from(x[0]).into_py(py));
+    }
+    P::to_array(py, x, shape)
+}
+
+/// Apply a function to every element of an array of (dual) numbers.
+fn map<P: PyDual>(py: Python, x: &PyAny, f: impl Fn(P::Data) -> P::Data) -> PyResult<PyObject> {
+    let (shape, x) = extract_array::<P>(x)?;
+    to_object::<P>(py, &shape, x.into_iter().map(f).collect())
+}
+
+fn sum_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let res = extract_array::<P>(a)?
+        .1
+        .into_iter()
+        .fold(P::Data::from(0.0), |acc, x| acc + x);
+    Ok(P::from(res).into_py(py))
+}
+
+fn prod_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let res = extract_array::<P>(a)?
+        .1
+        .into_iter()
+        .fold(P::Data::from(1.0), |acc, x| acc * x);
+    Ok(P::from(res).into_py(py))
+}
+
+fn dot_generic<P: PyDual>(py: Python, a: &PyAny, b: &PyAny) -> PyResult<PyObject> {
+    let (sa, a) = extract_array::<P>(a)?;
+    let (sb, b) = extract_array::<P>(b)?;
+    if sa.is_empty() {
+        return to_object::<P>(py, &sb, b.into_iter().map(|b| a[0] * b).collect());
+    }
+    if sb.is_empty() {
+        return to_object::<P>(py, &sa, a.into_iter().map(|a| a * b[0]).collect());
+    }
+    // vectors are treated as row (a) and column (b) matrices
+    let not_aligned =
+        || PyErr::new::<PyValueError, _>(format!("shapes {:?} and {:?} not aligned!", sa, sb));
+    let (m, n) = match *sa.as_slice() {
+        [n] => (1, n),
+        [m, n] => (m, n),
+        _ => return Err(not_aligned()),
+    };
+    let (l, k) = match *sb.as_slice() {
+        [l] => (l, 1),
+        [l, k] => (l, k),
+        _ => return Err(not_aligned()),
+    };
+    if n != l {
+        return Err(not_aligned());
+    }
+    let mut res = Vec::with_capacity(m * k);
+    for i in 0..m {
+        for j in 0..k {
+            res.push((0..n).fold(P::Data::from(0.0), |acc, s| {
+                acc + a[i * n + s] * b[s * k + j]
+            }));
+        }
+    }
+    let shape: Vec<usize> = sa[..sa.len() - 1].iter().chain(&sb[1..]).copied().collect();
+    to_object::<P>(py, &shape, res)
+}
+
+fn interp_generic<P: PyDual>(py: Python, x: &PyAny, xp: &PyAny, fp: &PyAny) -> PyResult<PyObject> {
+    let xp = extract_real_vec(xp)?;
+    let (_, fp) = extract_array::<P>(fp)?;
+    if xp.is_empty() || xp.len() != fp.len() {
+        return Err(PyErr::new::<PyValueError, _>(
+            "fp and xp are not of the same length!",
+        ));
+    }
+    if xp.iter().any(|x| x.is_nan()) || xp.windows(2).any(|w| w[0] > w[1]) {
+        return Err(PyErr::new::<PyValueError, _>(
+            "xp has to be increasing and must not contain NaN!",
+        ));
+    }
+    let n = xp.len();
+    map::<P>(py, x, |x| {
+        let re = x.re();
+        if re.is_nan() {
+            return P::Data::from(f64::NAN);
+        }
+        if re <= xp[0] {
+            return fp[0];
+        }
+        if re >= xp[n - 1] {
+            return fp[n - 1];
+        }
+        match xp.windows(2).position(|w| re < w[1]) {
+            Some(i) => fp[i] + (fp[i + 1] - fp[i]) * ((x - xp[i]) / (xp[i + 1] - xp[i])),
+            None => fp[n - 1],
+        }
+    })
+}
+
+fn polyval_generic<P: PyDual>(py: Python, p: &PyAny, x: &PyAny) -> PyResult<PyObject> {
+    let (_, p) = extract_array::<P>(p)?;
+    map::<P>(py, x, |x| {
+        p.iter().fold(P::Data::from(0.0), |acc, &c| acc * x + c)
+    })
+}
+
+fn norm_generic<P: PyDual>(py: Python, x: &PyAny) -> PyResult<PyObject> {
+    let (_, x) = extract_array::<P>(x)?;
+    let res = match x.as_slice() {
+        [x] if x.re() < 0.0 => -*x,
+        [x] => *x,
+        x => x
+            .iter()
+            .fold(P::Data::from(0.0), |acc, &x| acc + x * x)
+            .sqrt(),
+    };
+    Ok(P::from(res).into_py(py))
+}
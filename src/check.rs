@@ -0,0 +1,328 @@
+//! Comparison of derivatives from dual numbers with finite differences.
+use crate::coerce::Variables;
+use crate::derivatives::{gradient, hessian, third_derivative, value_at};
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyComplex;
+
+/// Central difference approximation of the partial derivative with respect
+/// to the variables in `index` (repeated indices for higher derivatives).
+fn central_difference(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    index: &[usize],
+    h: &[f64],
+) -> PyResult<f64> {
+    let mut sum = 0.0;
+    for signs in 0..(1 << index.len()) {
+        let mut xs = x.values().to_vec();
+        let mut sign = 1.0;
+        for (b, &i) in index.iter().enumerate() {
+            let s = if signs & (1 << b) != 0 { -1.0 } else { 1.0 };
+            xs[i] += s * h[i];
+            sign *= s;
+        }
+        sum += sign * value_at(py, f, x, &xs)?;
+    }
+    let den: f64 = index.iter().map(|&i| 2.0 * h[i]).product();
+    Ok(sum / den)
+}
+
+/// Complex-step approximation of the partial derivative with respect to variable `i`.
+fn complex_step(py: Python, f: &PyAny, x: &Variables, i: usize) -> PyResult<f64> {
+    let h = 1e-20 * x.values()[i].abs().max(1.0);
+    let args = x
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(k, &x)| PyComplex::from_doubles(py, x, if k == i { h } else { 0.0 }).into_py(py))
+        .collect();
+    let y = f.call1((x.wrap(py, args)?,))?;
+    Ok(y.getattr("imag")?.extract::<f64>()? / h)
+}
+
+/// Multi-index of a flat index of an array with `order` axes of length `n`.
+fn unravel(mut flat: usize, n: usize, order: usize) -> Vec<usize> {
+    let mut index = vec![0; order];
+    for i in index.iter_mut().rev() {
+        *i = flat % n;
+        flat /= n;
+    }
+    index
+}
+
+#[pyclass(name = "DerivativeComparison")]
+#[derive(Clone)]
+/// Derivatives of one order from dual numbers and from finite differences.
+pub struct PyDerivativeComparison {
+    order: usize,
+    n: usize,
+    shape: Vec<usize>,
+    ad: Vec<f64>,
+    fd: Vec<f64>,
+    rtol: f64,
+    atol: f64,
+}
+
+impl PyDerivativeComparison {
+    fn abs_errors(&self) -> Vec<f64> {
+        self.ad
+            .iter()
+            .zip(&self.fd)
+            .map(|(a, f)| (a - f).abs())
+            .collect()
+    }
+
+    fn rel_errors(&self) -> Vec<f64> {
+        self.ad
+            .iter()
+            .zip(&self.fd)
+            .map(|(a, f)| {
+                let scale = a.abs().max(f.abs());
+                if scale == 0.0 {
+                    0.0
+                } else {
+                    (a - f).abs() / scale
+                }
+            })
+            .collect()
+    }
+
+    fn mismatches(&self) -> Vec<bool> {
+        self.ad
+            .iter()
+            .zip(&self.fd)
+            .map(|(a, f)| {
+                let error = (a - f).abs();
+                error.is_nan() || error > self.atol + self.rtol * f.abs()
+            })
+            .collect()
+    }
+
+    fn array<T: numpy::Element>(&self, py: Python, x: Vec<T>) -> PyResult<PyObject> {
+        Ok(PyArray1::from_vec(py, x)
+            .reshape(self.shape.as_slice())?
+            .to_object(py))
+    }
+}
+
+#[pymethods]
+impl PyDerivativeComparison {
+    #[getter]
+    /// Order of the derivatives.
+    pub fn get_order(&self) -> usize {
+        self.order
+    }
+
+    #[getter]
+    /// Derivatives calculated with dual numbers.
+    pub fn get_ad(&self, py: Python) -> PyResult<PyObject> {
+        self.array(py, self.ad.clone())
+    }
+
+    #[getter]
+    /// Derivatives calculated with finite differences.
+    pub fn get_fd(&self, py: Python) -> PyResult<PyObject> {
+        self.array(py, self.fd.clone())
+    }
+
+    #[getter]
+    /// Absolute errors |ad - fd|.
+    pub fn get_abs_error(&self, py: Python) -> PyResult<PyObject> {
+        self.array(py, self.abs_errors())
+    }
+
+    #[getter]
+    /// Relative errors |ad - fd| / max(|ad|, |fd|).
+    pub fn get_rel_error(&self, py: Python) -> PyResult<PyObject> {
+        self.array(py, self.rel_errors())
+    }
+
+    #[getter]
+    /// Entries for which |ad - fd| > atol + rtol * |fd|.
+    pub fn get_mismatch(&self, py: Python) -> PyResult<PyObject> {
+        self.array(py, self.mismatches())
+    }
+
+    #[getter]
+    /// Whether all entries agree within the tolerances.
+    pub fn get_passed(&self) -> bool {
+        !self.mismatches().into_iter().any(|m| m)
+    }
+}
+
+#[pyclass(name = "DerivativeCheck")]
+/// Result of `check_derivatives`.
+pub struct PyDerivativeCheck {
+    value: f64,
+    comparisons: Vec<PyDerivativeComparison>,
+}
+
+#[pymethods]
+impl PyDerivativeCheck {
+    #[getter]
+    /// Function value.
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    #[getter]
+    /// Comparison of the gradients.
+    pub fn get_gradient(&self) -> PyDerivativeComparison {
+        self.comparisons[0].clone()
+    }
+
+    #[getter]
+    /// Comparison of the Hessians, if calculated.
+    pub fn get_hessian(&self) -> Option<PyDerivativeComparison> {
+        self.comparisons.get(1).cloned()
+    }
+
+    #[getter]
+    /// Comparison of the third derivatives, if calculated.
+    pub fn get_third_derivative(&self) -> Option<PyDerivativeComparison> {
+        self.comparisons.get(2).cloned()
+    }
+
+    #[getter]
+    /// Whether all derivatives agree within the tolerances.
+    pub fn get_passed(&self) -> bool {
+        self.comparisons.iter().all(|c| c.get_passed())
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyDerivativeCheck {
+    fn __repr__(&self) -> PyResult<String> {
+        let mut lines = vec![
+            format!("DerivativeCheck(passed={})", self.get_passed()),
+            format!(
+                "{:<6}{:<12}{:>16}{:>16}{:>12}{:>12}",
+                "order", "index", "ad", "fd", "abs. error", "rel. error"
+            ),
+        ];
+        for c in &self.comparisons {
+            let (abs, rel, mismatch) = (c.abs_errors(), c.rel_errors(), c.mismatches());
+            for (k, (&ad, &fd)) in c.ad.iter().zip(&c.fd).enumerate() {
+                lines.push(format!(
+                    "{:<6}{:<12}{:>16.8e}{:>16.8e}{:>12.2e}{:>12.2e}{}",
+                    c.order,
+                    format!("{:?}", unravel(k, c.n, c.order)),
+                    ad,
+                    fd,
+                    abs[k],
+                    rel[k],
+                    if mismatch[k] { "  <-- mismatch" } else { "" }
+                ));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[pyfunction(order = "1", method = "\"central\"", rtol = "1e-4", atol = "1e-8")]
+#[pyo3(text_signature = "(f, x, order=1, method='central', rtol=1e-4, atol=1e-8)")]
+/// Compare derivatives calculated with dual numbers with finite differences.
+///
+/// The derivatives are calculated by evaluating `f` with dual numbers
+/// (gradient), hyper dual numbers (Hessian) and hyper dual numbers of
+/// dual numbers (third derivatives) and compared to finite differences
+/// of `f` evaluated with floats.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Scalar function, called with the same structure as `x`.
+/// x : float | list[float] | numpy.ndarray
+///     Point at which the derivatives are compared.
+/// order : int, optional
+///     Highest order of derivatives (1 to 3), defaults to 1.
+/// method : str, optional
+///     'central' (central differences) or 'complex' (complex step for
+///     the gradient, `f` has to accept complex numbers). Higher derivatives
+///     are always approximated with central differences.
+/// rtol : float, optional
+///     Relative tolerance, defaults to 1e-4.
+/// atol : float, optional
+///     Absolute tolerance, defaults to 1e-8.
+///
+/// Returns
+/// -------
+/// DerivativeCheck
+///     Entries with |ad - fd| > atol + rtol * |fd| are flagged as mismatch.
+///
+/// Examples
+///
+/// >>> from dualnum import check_derivatives
+/// >>> import numpy as np
+/// >>> check_derivatives(lambda x: np.exp(x[0]) * np.sin(x[1]), [1.0, 2.0], order=2).passed
+/// True
+pub fn check_derivatives(
+    py: Python,
+    f: &PyAny,
+    x: &PyAny,
+    order: usize,
+    method: &str,
+    rtol: f64,
+    atol: f64,
+) -> PyResult<PyDerivativeCheck> {
+    if !(1..=3).contains(&order) {
+        return Err(PyErr::new::<PyValueError, _>("order has to be 1, 2 or 3!"));
+    }
+    if method != "central" && method != "complex" {
+        return Err(PyErr::new::<PyValueError, _>(format!(
+            "unknown method '{}', expected 'central' or 'complex'",
+            method
+        )));
+    }
+    let x = Variables::extract(x)?;
+    let n = x.values().len();
+    let shape = x.shape();
+    let (value, grad, hess) = if order == 1 {
+        let (v, g) = gradient(py, f, &x)?;
+        (v, g, None)
+    } else {
+        let (v, g, h) = hessian(py, f, &x)?;
+        (v, g, Some(h.concat()))
+    };
+
+    let comparison = |order: usize, ad: Vec<f64>, fd: Vec<f64>| PyDerivativeComparison {
+        order,
+        n,
+        shape: shape.repeat(order),
+        ad,
+        fd,
+        rtol,
+        atol,
+    };
+    let step = |order: usize| -> Vec<f64> {
+        let c = f64::EPSILON.powf(1.0 / (order + 2) as f64);
+        x.values().iter().map(|x| c * x.abs().max(1.0)).collect()
+    };
+
+    let h = step(1);
+    let fd = (0..n)
+        .map(|i| match method {
+            "complex" => complex_step(py, f, &x, i),
+            _ => central_difference(py, f, &x, &[i], &h),
+        })
+        .collect::<PyResult<_>>()?;
+    let mut comparisons = vec![comparison(1, grad, fd)];
+    if let Some(hess) = hess {
+        let h = step(2);
+        let fd = (0..n * n)
+            .map(|k| central_difference(py, f, &x, &unravel(k, n, 2), &h))
+            .collect::<PyResult<_>>()?;
+        comparisons.push(comparison(2, hess, fd));
+    }
+    if order == 3 {
+        let h = step(3);
+        let fd = (0..n * n * n)
+            .map(|k| central_difference(py, f, &x, &unravel(k, n, 3), &h))
+            .collect::<PyResult<_>>()?;
+        comparisons.push(comparison(3, third_derivative(py, f, &x)?, fd));
+    }
+    Ok(PyDerivativeCheck { value, comparisons })
+}
@@ -0,0 +1,524 @@
+//! Generation of Rust and C source code from expression graphs.
+use crate::graph::{BinaryOp, Graph, Node, UnaryOp};
+use std::fmt::Write;
+
+fn rust_float(c: f64) -> String {
+    if c.is_nan() {
+        String::from("f64::NAN")
+    } else if c.is_infinite() {
+        String::from(if c > 0.0 { "f64::INFINITY" } else { "f64::NEG_INFINITY" })
+    } else {
+        format!("{:?}", c)
+    }
+}
+
+fn c_float(c: f64) -> String {
+    if c.is_nan() {
+        String::from("NAN")
+    } else if c.is_infinite() {
+        String::from(if c > 0.0 { "INFINITY" } else { "-INFINITY" })
+    } else {
+        format!("{:?}", c)
+    }
+}
+
+fn rust_method(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Neg | UnaryOp::Abs => unreachable!(),
+        UnaryOp::Recip => "recip",
+        UnaryOp::Sqrt => "sqrt",
+        UnaryOp::Cbrt => "cbrt",
+        UnaryOp::Exp => "exp",
+        UnaryOp::Exp2 => "exp2",
+        UnaryOp::ExpM1 => "exp_m1",
+        UnaryOp::Ln => "ln",
+        UnaryOp::Log2 => "log2",
+        UnaryOp::Log10 => "log10",
+        UnaryOp::Ln1p => "ln_1p",
+        UnaryOp::Sin => "sin",
+        UnaryOp::Cos => "cos",
+        UnaryOp::Tan => "tan",
+        UnaryOp::Asin => "asin",
+        UnaryOp::Acos => "acos",
+        UnaryOp::Atan => "atan",
+        UnaryOp::Sinh => "sinh",
+        UnaryOp::Cosh => "cosh",
+        UnaryOp::Tanh => "tanh",
+        UnaryOp::Asinh => "asinh",
+        UnaryOp::Acosh => "acosh",
+        UnaryOp::Atanh => "atanh",
+        UnaryOp::SphJ0 => "sph_j0",
+        UnaryOp::SphJ1 => "sph_j1",
+        UnaryOp::SphJ2 => "sph_j2",
+    }
+}
+
+/// Rust function that evaluates the graph for any type implementing `DualNum<f64>`.
+///
+/// The function takes the inputs as an array and returns an array of the
+/// outputs. Nodes that no output depends on are skipped.
+pub fn rust_source(graph: &Graph, name: &str) -> String {
+    let (n, m) = (graph.inputs, graph.outputs.len());
+    let reachable = graph.reachable();
+    let uses_inputs = graph
+        .nodes
+        .iter()
+        .zip(&reachable)
+        .any(|(node, &r)| r && matches!(node, Node::Input(_)));
+    let x = if uses_inputs { "x" } else { "_x" };
+    let mut code = String::from("use num_dual::DualNum;\n\n");
+    writeln!(
+        code,
+        "pub fn {}<D: DualNum<f64> + From<f64> + Copy>({}: [D; {}]) -> [D; {}] {{",
+        name, x, n, m
+    )
+    .unwrap();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        if !reachable[i] {
+            continue;
+        }
+        let v = |k: usize| format!("v{}", k);
+        let expr = match *node {
+            Node::Input(k) => format!("x[{}]", k),
+            Node::Const(c) => format!("D::from({})", rust_float(c)),
+            Node::Unary(UnaryOp::Neg, a) => format!("-{}", v(a)),
+            Node::Unary(UnaryOp::Abs, a) => format!(
+                "if {a}.re() < 0.0 {{ -{a} }} else {{ {a} }}",
+                a = v(a)
+            ),
+            Node::Unary(op, a) => format!("{}.{}()", v(a), rust_method(op)),
+            Node::Binary(BinaryOp::Pow, a, b) => format!("({} * {}.ln()).exp()", v(b), v(a)),
+            Node::Binary(op, a, b) => format!("{} {} {}", v(a), op.symbol(), v(b)),
+            Node::Powi(a, k) => format!("{}.powi({})", v(a), k),
+            Node::Powf(a, e) => format!("{}.powf({})", v(a), rust_float(e)),
+            Node::Log(a, base) => format!("{}.log({})", v(a), rust_float(base)),
+            Node::MulAdd(a, b, c) => format!("{}.mul_add({}, {})", v(a), v(b), v(c)),
+        };
+        writeln!(code, "    let v{} = {};", i, expr).unwrap();
+    }
+    let outputs: Vec<_> = graph.outputs.iter().map(|o| format!("v{}", o)).collect();
+    writeln!(code, "    [{}]\n}}", outputs.join(", ")).unwrap();
+    code
+}
+
+/// Value of a node and its first and second partial derivatives with respect
+/// to its operands as C expressions.
+///
+/// `a` are the names of the operands and `v` is the name of the value of the
+/// node.
+/// Vanishing partial derivatives are omitted, the second partial derivatives
+/// are given for operand pairs (i, j) with i <= j.
+#[allow(clippy::type_complexity)]
+fn c_partials(
+    node: &Node,
+    a: &[String],
+    v: &str,
+) -> (String, Vec<(usize, String)>, Vec<(usize, usize, String)>) {
+    const LN2: f64 = std::f64::consts::LN_2;
+    const LN10: f64 = std::f64::consts::LN_10;
+    let unary = |value: String, d1: String, d2: Option<String>| {
+        let second = d2.map(|d2| vec![(0, 0, d2)]).unwrap_or_default();
+        (value, vec![(0, d1)], second)
+    };
+    match *node {
+        Node::Input(_) | Node::Const(_) => unreachable!(),
+        Node::Unary(op, _) => {
+            let x = &a[0];
+            match op {
+                UnaryOp::Neg => unary(format!("-{}", x), "-1.0".into(), None),
+                UnaryOp::Abs => unary(
+                    format!("fabs({})", x),
+                    format!("({} < 0.0 ? -1.0 : 1.0)", x),
+                    None,
+                ),
+                UnaryOp::Recip => unary(
+                    format!("1.0 / {}", x),
+                    format!("-{v} * {v}", v = v),
+                    Some(format!("2.0 * {v} * {v} * {v}", v = v)),
+                ),
+                UnaryOp::Sqrt => unary(
+                    format!("sqrt({})", x),
+                    format!("0.5 / {}", v),
+                    Some(format!("-0.25 / ({} * {})", x, v)),
+                ),
+                UnaryOp::Cbrt => unary(
+                    format!("cbrt({})", x),
+                    format!("{} / (3.0 * {})", v, x),
+                    Some(format!("-2.0 * {v} / (9.0 * {x} * {x})", v = v, x = x)),
+                ),
+                UnaryOp::Exp => unary(format!("exp({})", x), v.into(), Some(v.into())),
+                UnaryOp::Exp2 => unary(
+                    format!("exp2({})", x),
+                    format!("{:?} * {}", LN2, v),
+                    Some(format!("{:?} * {}", LN2 * LN2, v)),
+                ),
+                UnaryOp::ExpM1 => unary(
+                    format!("expm1({})", x),
+                    format!("{} + 1.0", v),
+                    Some(format!("{} + 1.0", v)),
+                ),
+                UnaryOp::Ln => unary(
+                    format!("log({})", x),
+                    format!("1.0 / {}", x),
+                    Some(format!("-1.0 / ({x} * {x})", x = x)),
+                ),
+                UnaryOp::Log2 => unary(
+                    format!("log2({})", x),
+                    format!("1.0 / ({} * {:?})", x, LN2),
+                    Some(format!("-1.0 / ({x} * {x} * {:?})", LN2, x = x)),
+                ),
+                UnaryOp::Log10 => unary(
+                    format!("log10({})", x),
+                    format!("1.0 / ({} * {:?})", x, LN10),
+                    Some(format!("-1.0 / ({x} * {x} * {:?})", LN10, x = x)),
+                ),
+                UnaryOp::Ln1p => unary(
+                    format!("log1p({})", x),
+                    format!("1.0 / (1.0 + {})", x),
+                    Some(format!("-1.0 / ((1.0 + {x}) * (1.0 + {x}))", x = x)),
+                ),
+                UnaryOp::Sin => unary(
+                    format!("sin({})", x),
+                    format!("cos({})", x),
+                    Some(format!("-{}", v)),
+                ),
+                UnaryOp::Cos => unary(
+                    format!("cos({})", x),
+                    format!("-sin({})", x),
+                    Some(format!("-{}", v)),
+                ),
+                UnaryOp::Tan => unary(
+                    format!("tan({})", x),
+                    format!("1.0 + {v} * {v}", v = v),
+                    Some(format!("2.0 * {v} * (1.0 + {v} * {v})", v = v)),
+                ),
+                UnaryOp::Asin => unary(
+                    format!("asin({})", x),
+                    format!("1.0 / sqrt(1.0 - {x} * {x})", x = x),
+                    Some(format!("{x} / pow(1.0 - {x} * {x}, 1.5)", x = x)),
+                ),
+                UnaryOp::Acos => unary(
+                    format!("acos({})", x),
+                    format!("-1.0 / sqrt(1.0 - {x} * {x})", x = x),
+                    Some(format!("-{x} / pow(1.0 - {x} * {x}, 1.5)", x = x)),
+                ),
+                UnaryOp::Atan => unary(
+                    format!("atan({})", x),
+                    format!("1.0 / (1.0 + {x} * {x})", x = x),
+                    Some(format!(
+                        "-2.0 * {x} / ((1.0 + {x} * {x}) * (1.0 + {x} * {x}))",
+                        x = x
+                    )),
+                ),
+                UnaryOp::Sinh => unary(
+                    format!("sinh({})", x),
+                    format!("cosh({})", x),
+                    Some(v.into()),
+                ),
+                UnaryOp::Cosh => unary(
+                    format!("cosh({})", x),
+                    format!("sinh({})", x),
+                    Some(v.into()),
+                ),
+                UnaryOp::Tanh => unary(
+                    format!("tanh({})", x),
+                    format!("1.0 - {v} * {v}", v = v),
+                    Some(format!("-2.0 * {v} * (1.0 - {v} * {v})", v = v)),
+                ),
+                UnaryOp::Asinh => unary(
+                    format!("asinh({})", x),
+                    format!("1.0 / sqrt({x} * {x} + 1.0)", x = x),
+                    Some(format!("-{x} / pow({x} * {x} + 1.0, 1.5)", x = x)),
+                ),
+                UnaryOp::Acosh => unary(
+                    format!("acosh({})", x),
+                    format!("1.0 / sqrt({x} * {x} - 1.0)", x = x),
+                    Some(format!("-{x} / pow({x} * {x} - 1.0, 1.5)", x = x)),
+                ),
+                UnaryOp::Atanh => unary(
+                    format!("atanh({})", x),
+                    format!("1.0 / (1.0 - {x} * {x})", x = x),
+                    Some(format!(
+                        "2.0 * {x} / ((1.0 - {x} * {x}) * (1.0 - {x} * {x}))",
+                        x = x
+                    )),
+                ),
+                // The derivatives follow from the recurrence
+                // (2n + 1) j_n' = n j_(n-1) - (n + 1) j_(n+1), which, unlike
+                // the differential equation, does not divide by x.
+                UnaryOp::SphJ0 => unary(
+                    format!("sph_j(0, {})", x),
+                    format!("-sph_j(1, {})", x),
+                    Some(format!("(2.0 * sph_j(2, {x}) - {v}) / 3.0", x = x, v = v)),
+                ),
+                UnaryOp::SphJ1 => unary(
+                    format!("sph_j(1, {})", x),
+                    format!("(sph_j(0, {x}) - 2.0 * sph_j(2, {x})) / 3.0", x = x),
+                    Some(format!(
+                        "(2.0 * sph_j(3, {x}) - 3.0 * {v}) / 5.0",
+                        x = x,
+                        v = v
+                    )),
+                ),
+                UnaryOp::SphJ2 => unary(
+                    format!("sph_j(2, {})", x),
+                    format!("(2.0 * sph_j(1, {x}) - 3.0 * sph_j(3, {x})) / 5.0", x = x),
+                    Some(format!(
+                        "(14.0 * sph_j(0, {x}) - 55.0 * {v} + 36.0 * sph_j(4, {x})) / 105.0",
+                        x = x,
+                        v = v
+                    )),
+                ),
+            }
+        }
+        // Vanishing derivatives of constant and linear powers are given
+        // explicitly, pow(0.0, -1) would turn them into NaN at zero.
+        Node::Powi(_, 0) => unary("1.0".into(), "0.0".into(), None),
+        Node::Powi(_, 1) => unary(a[0].clone(), "1.0".into(), None),
+        Node::Powi(_, k) => unary(
+            format!("pow({}, {})", a[0], k),
+            format!("{}.0 * pow({}, {})", k, a[0], k as i64 - 1),
+            Some(format!(
+                "{}.0 * pow({}, {})",
+                k as i64 * (k as i64 - 1),
+                a[0],
+                k as i64 - 2
+            )),
+        ),
+        Node::Powf(x, e) if e == 0.0 || e == 1.0 => c_partials(&Node::Powi(x, e as i32), a, v),
+        Node::Powf(_, e) => unary(
+            format!("pow({}, {})", a[0], c_float(e)),
+            format!("{} * pow({}, {})", c_float(e), a[0], c_float(e - 1.0)),
+            Some(format!(
+                "{} * pow({}, {})",
+                c_float(e * (e - 1.0)),
+                a[0],
+                c_float(e - 2.0)
+            )),
+        ),
+        Node::Log(_, base) => unary(
+            format!("log({}) / log({})", a[0], c_float(base)),
+            format!("1.0 / ({} * log({}))", a[0], c_float(base)),
+            Some(format!("-1.0 / ({x} * {x} * log({}))", c_float(base), x = a[0])),
+        ),
+        Node::Binary(op, _, _) => {
+            let (x, y) = (&a[0], &a[1]);
+            match op {
+                BinaryOp::Add => (
+                    format!("{} + {}", x, y),
+                    vec![(0, "1.0".into()), (1, "1.0".into())],
+                    vec![],
+                ),
+                BinaryOp::Sub => (
+                    format!("{} - {}", x, y),
+                    vec![(0, "1.0".into()), (1, "-1.0".into())],
+                    vec![],
+                ),
+                BinaryOp::Mul => (
+                    format!("{} * {}", x, y),
+                    vec![(0, y.clone()), (1, x.clone())],
+                    vec![(0, 1, "1.0".into())],
+                ),
+                BinaryOp::Div => (
+                    format!("{} / {}", x, y),
+                    vec![(0, format!("1.0 / {}", y)), (1, format!("-{} / {}", v, y))],
+                    vec![
+                        (0, 1, format!("-1.0 / ({y} * {y})", y = y)),
+                        (1, 1, format!("2.0 * {v} / ({y} * {y})", v = v, y = y)),
+                    ],
+                ),
+                BinaryOp::Pow => (
+                    format!("pow({}, {})", x, y),
+                    vec![
+                        (0, format!("{} * {} / {}", y, v, x)),
+                        (1, format!("{} * log({})", v, x)),
+                    ],
+                    vec![
+                        (
+                            0,
+                            0,
+                            format!("{y} * ({y} - 1.0) * {v} / ({x} * {x})", x = x, y = y, v = v),
+                        ),
+                        (
+                            0,
+                            1,
+                            format!("{v} / {x} * (1.0 + {y} * log({x}))", x = x, y = y, v = v),
+                        ),
+                        (1, 1, format!("{v} * log({x}) * log({x})", x = x, v = v)),
+                    ],
+                ),
+            }
+        }
+        Node::MulAdd(_, _, _) => (
+            format!("{} * {} + {}", a[0], a[1], a[2]),
+            vec![(0, a[1].clone()), (1, a[0].clone()), (2, "1.0".into())],
+            vec![(0, 1, "1.0".into())],
+        ),
+    }
+}
+
+// Spherical Bessel functions of the first kind. The power series is used
+// for |x| < 2, where the closed forms suffer from cancellation, and the
+// upward recurrence otherwise.
+const C_SPH_J: &str = "static double sph_j(int n, double x) {
+    int k;
+    if (fabs(x) < 2.0) {
+        double p = 1.0, t = 1.0, s = 1.0;
+        for (k = 1; k <= n; k++) p *= x / (2 * k + 1);
+        for (k = 1; k <= 20; k++) {
+            t *= -x * x / (2.0 * k * (2 * n + 2 * k + 1));
+            s += t;
+        }
+        return p * s;
+    }
+    double a = sin(x) / x, b = (a - cos(x)) / x, c;
+    if (n == 0) return a;
+    for (k = 1; k < n; k++) {
+        c = (2 * k + 1) * b / x - a;
+        a = b;
+        b = c;
+    }
+    return b;
+}
+
+";
+
+/// C function that evaluates the graph and its first and second partial
+/// derivatives.
+///
+/// The generated function has the signature
+/// `void name(const double *x, double *f, double *grad, double *hess)`,
+/// where `grad` (row-major, outputs x inputs) is only present for
+/// `order >= 1` and `hess` (outputs x inputs x inputs) only for `order == 2`.
+/// Nodes that no output depends on are skipped.
+pub fn c_source(graph: &Graph, name: &str, order: usize) -> String {
+    let n = graph.inputs;
+    let order = order.min(2);
+    let reachable = graph.reachable();
+    let mut code = String::from("#include <math.h>\n\n");
+    if graph.nodes.iter().zip(&reachable).any(|(node, &r)| {
+        r && matches!(
+            node,
+            Node::Unary(UnaryOp::SphJ0, _) | Node::Unary(UnaryOp::SphJ1, _) | Node::Unary(UnaryOp::SphJ2, _)
+        )
+    }) {
+        code.push_str(C_SPH_J);
+    }
+    let mut params = String::from("const double *x, double *f");
+    if order >= 1 {
+        params.push_str(", double *grad");
+    }
+    if order >= 2 {
+        params.push_str(", double *hess");
+    }
+    writeln!(code, "void {}({}) {{", name, params).unwrap();
+    if order >= 1 {
+        writeln!(code, "    int i, j;").unwrap();
+    }
+    // Nodes that do not depend on any input have vanishing derivatives.
+    let mut active = vec![false; graph.nodes.len()];
+    for (i, node) in graph.nodes.iter().enumerate() {
+        if !reachable[i] {
+            continue;
+        }
+        let v = format!("v{}", i);
+        match *node {
+            Node::Input(k) => {
+                active[i] = true;
+                writeln!(code, "    const double {} = x[{}];", v, k).unwrap();
+                if order >= 1 {
+                    writeln!(code, "    double g{}[{}] = {{0.0}};", i, n).unwrap();
+                    writeln!(code, "    g{}[{}] = 1.0;", i, k).unwrap();
+                }
+                if order >= 2 {
+                    writeln!(code, "    double h{}[{}][{}] = {{{{0.0}}}};", i, n, n).unwrap();
+                }
+            }
+            Node::Const(c) => {
+                writeln!(code, "    const double {} = {};", v, c_float(c)).unwrap();
+            }
+            _ => {
+                let operands = node.operands();
+                let names: Vec<_> = operands.iter().map(|o| format!("v{}", o)).collect();
+                let d = format!("d{}", i);
+                let (value, first, second) = c_partials(node, &names, &v);
+                writeln!(code, "    const double {} = {};", v, value).unwrap();
+                active[i] = operands.iter().any(|&o| active[o]);
+                if order == 0 || !active[i] {
+                    continue;
+                }
+                let first: Vec<_> = first.into_iter().filter(|(k, _)| active[operands[*k]]).collect();
+                for (k, expr) in &first {
+                    writeln!(code, "    const double {}_{} = {};", d, k, expr).unwrap();
+                }
+                let terms: Vec<_> = first
+                    .iter()
+                    .map(|(k, _)| format!("{}_{} * g{}[i]", d, k, operands[*k]))
+                    .collect();
+                writeln!(code, "    double g{}[{}];", i, n).unwrap();
+                writeln!(
+                    code,
+                    "    for (i = 0; i < {}; i++) g{}[i] = {};",
+                    n,
+                    i,
+                    terms.join(" + ")
+                )
+                .unwrap();
+                if order < 2 {
+                    continue;
+                }
+                let mut terms: Vec<_> = first
+                    .iter()
+                    .map(|(k, _)| format!("{}_{} * h{}[i][j]", d, k, operands[*k]))
+                    .collect();
+                for (k, l, expr) in second {
+                    let (ok, ol) = (operands[k], operands[l]);
+                    if !active[ok] || !active[ol] {
+                        continue;
+                    }
+                    writeln!(code, "    const double {}_{}{} = {};", d, k, l, expr).unwrap();
+                    terms.push(if k == l {
+                        format!("{}_{}{} * g{}[i] * g{}[j]", d, k, l, ok, ok)
+                    } else {
+                        format!(
+                            "{}_{}{} * (g{}[i] * g{}[j] + g{}[i] * g{}[j])",
+                            d, k, l, ok, ol, ol, ok
+                        )
+                    });
+                }
+                writeln!(code, "    double h{}[{}][{}];", i, n, n).unwrap();
+                writeln!(
+                    code,
+                    "    for (i = 0; i < {}; i++) for (j = 0; j < {}; j++) h{}[i][j] = {};",
+                    n,
+                    n,
+                    i,
+                    terms.join(" + ")
+                )
+                .unwrap();
+            }
+        }
+    }
+    for (k, &o) in graph.outputs.iter().enumerate() {
+        writeln!(code, "    f[{}] = v{};", k, o).unwrap();
+        if order >= 1 {
+            let g = if active[o] { format!("g{}[i]", o) } else { "0.0".into() };
+            writeln!(code, "    for (i = 0; i < {}; i++) grad[{} + i] = {};", n, k * n, g).unwrap();
+        }
+        if order >= 2 {
+            let h = if active[o] { format!("h{}[i][j]", o) } else { "0.0".into() };
+            writeln!(
+                code,
+                "    for (i = 0; i < {}; i++) for (j = 0; j < {}; j++) hess[{} + i * {} + j] = {};",
+                n,
+                n,
+                k * n * n,
+                n,
+                h
+            )
+            .unwrap();
+        }
+    }
+    code.push_str("}\n");
+    code
+}
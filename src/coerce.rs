@@ -0,0 +1,280 @@
+//! Conversion of Python objects into real numbers.
+//!
+//! The same rules apply to all constructors, `from_re`, the arithmetic
+//! operators and the derive functions:
+//!
+//! * `float`, `int`, `bool` and every object implementing `__float__` or
+//!   `__index__` (e.g. numpy scalars) are real numbers.
+//! * Objects with an `ndim` attribute (numpy arrays) are real numbers only if
+//!   they are zero-dimensional.
+//! * Sequences of real numbers (lists, tuples) and arrays of any shape are
+//!   accepted wherever the derive functions expect multiple variables.
+//! * Integer exponents are objects implementing `__index__` and are evaluated
+//!   with `powi`. Integer exponents that do not fit into a 32-bit integer
+//!   raise an OverflowError, all other real exponents are evaluated with
+//!   `powf`.
+use crate::array_function::object_array;
+use num_dual::DualNum;
+use numpy::{PyArray1, PyReadonlyArrayDyn};
+use pyo3::exceptions::{PyOverflowError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyFloat, PyLong, PyString};
+
+/// Extract a real number from a Python object.
+pub fn extract_real(obj: &PyAny) -> PyResult<f64> {
+    if let Ok(x) = obj.downcast::<PyFloat>() {
+        return Ok(x.value());
+    }
+    if obj.is_instance::<PyLong>()? {
+        return obj.extract::<f64>();
+    }
+    if obj.is_instance::<PyString>()? || obj.is_instance::<PyBytes>()? {
+        return Err(not_a_real(obj));
+    }
+    if let Ok(ndim) = obj.getattr("ndim") {
+        if ndim.extract::<usize>()? != 0 {
+            return Err(PyErr::new::<PyTypeError, _>(format!(
+                "expected a real number, got an array of dimension {}",
+                ndim
+            )));
+        }
+    }
+    if let Ok(x) = obj.extract::<f64>() {
+        return Ok(x);
+    }
+    if let Ok(x) = obj.call_method0("__index__") {
+        return x.extract::<f64>();
+    }
+    Err(not_a_real(obj))
+}
+
+/// Extract an integer exponent from a Python object implementing `__index__`.
+///
+/// Returns `None` for all other objects and an OverflowError for integers
+/// that do not fit into 32 bits.
+pub fn extract_exponent(obj: &PyAny) -> PyResult<Option<i32>> {
+    if obj.is_instance::<PyFloat>()? {
+        return Ok(None);
+    }
+    let index = match obj.call_method0("__index__") {
+        Ok(index) => index,
+        Err(_) => return Ok(None),
+    };
+    index.extract::<i32>().map(Some).map_err(|_| {
+        PyErr::new::<PyOverflowError, _>(format!(
+            "integer exponent {} does not fit into 32 bits!",
+            index
+        ))
+    })
+}
+
+/// Extract a sequence of real numbers from a Python object.
+pub fn extract_real_vec(obj: &PyAny) -> PyResult<Vec<f64>> {
+    if obj.is_instance::<PyString>()? || obj.is_instance::<PyBytes>()? {
+        return Err(not_a_real(obj));
+    }
+    if let Ok(ndim) = obj.getattr("ndim") {
+        if ndim.extract::<usize>()? != 1 {
+            return Err(PyErr::new::<PyTypeError, _>(format!(
+                "expected a sequence of real numbers, got an array of dimension {}",
+                ndim
+            )));
+        }
+    }
+    obj.iter()?.map(|x| extract_real(x?)).collect()
+}
+
+fn not_a_real(obj: &PyAny) -> PyErr {
+    let name = obj
+        .get_type()
+        .name()
+        .map(String::from)
+        .unwrap_or_else(|_| String::from("?"));
+    PyErr::new::<PyTypeError, _>(format!("expected a real number, got '{}'", name))
+}
+
+/// Conversion of a Python object into the field type of a dual number.
+pub trait FromPyReal: Sized {
+    fn from_py_real(obj: &PyAny) -> PyResult<Self>;
+}
+
+impl FromPyReal for f64 {
+    fn from_py_real(obj: &PyAny) -> PyResult<Self> {
+        extract_real(obj)
+    }
+}
+
+/// Independent variables passed to the derive functions.
+pub enum Variables {
+    Scalar(f64),
+    Sequence(Vec<f64>),
+    Array(Vec<usize>, Vec<f64>),
+}
+
+impl Variables {
+    /// Extract a real number, a sequence of real numbers or an array of any shape.
+    pub fn extract(obj: &PyAny) -> PyResult<Self> {
+        if let Ok(x) = extract_real(obj) {
+            return Ok(Self::Scalar(x));
+        }
+        if let Ok(ndim) = obj.getattr("ndim") {
+            if ndim.extract::<usize>()? > 0 {
+                let np = obj.py().import("numpy")?;
+                let x: PyReadonlyArrayDyn<f64> =
+                    np.call_method1("asarray", (obj, "f8"))?.extract()?;
+                let shape = x.shape().to_vec();
+                return Ok(Self::Array(shape, x.as_array().iter().copied().collect()));
+            }
+        }
+        Ok(Self::Sequence(extract_real_vec(obj)?))
+    }
+
+    /// Flattened values of the variables.
+    pub fn values(&self) -> &[f64] {
+        match self {
+            Self::Scalar(x) => std::slice::from_ref(x),
+            Self::Sequence(x) | Self::Array(_, x) => x,
+        }
+    }
+
+    /// Shape of the variables.
+    pub fn shape(&self) -> Vec<usize> {
+        match self {
+            Self::Scalar(_) => vec![],
+            Self::Sequence(x) => vec![x.len()],
+            Self::Array(shape, _) => shape.clone(),
+        }
+    }
+
+    /// Flattened values of the variables.
+    pub fn into_values(self) -> Vec<f64> {
+        match self {
+            Self::Scalar(x) => vec![x],
+            Self::Sequence(x) | Self::Array(_, x) => x,
+        }
+    }
+
+    /// Flattened mask of the variables that are seeded.
+    ///
+    /// If `seed` is `None`, all variables are seeded, otherwise `seed` has to
+    /// be a boolean (array) of the same shape as the variables that selects
+    /// at least one variable.
+    pub fn seed(&self, seed: Option<&PyAny>) -> PyResult<Vec<bool>> {
+        let seed = match seed {
+            None => return Ok(vec![true; self.values().len()]),
+            Some(seed) => seed,
+        };
+        let np = seed.py().import("numpy")?;
+        let seed: PyReadonlyArrayDyn<bool> = np.call_method1("asarray", (seed, "?"))?.extract()?;
+        let shape = self.shape();
+        if seed.shape() != shape.as_slice() {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "seed has shape {:?}, but the variables have shape {:?}",
+                seed.shape(),
+                shape
+            )));
+        }
+        let seed: Vec<bool> = seed.as_array().iter().copied().collect();
+        if !seed.contains(&true) {
+            return Err(PyErr::new::<PyValueError, _>(
+                "seed has to select at least one variable!",
+            ));
+        }
+        Ok(seed)
+    }
+
+    /// Arrange the (dual) numbers in the same structure as the variables.
+    pub fn wrap(&self, py: Python, x: Vec<PyObject>) -> PyResult<PyObject> {
+        match self {
+            Self::Scalar(_) => Ok(x[0].clone_ref(py)),
+            Self::Sequence(_) => Ok(x.to_object(py)),
+            Self::Array(shape, _) => {
+                let array = PyArray1::from_vec(py, x).reshape(shape.as_slice())?;
+                object_array(py, array.to_object(py).into_ref(py))
+            }
+        }
+    }
+}
+
+/// Entries of an array of (dual) numbers as flat vector and the shape of the array.
+pub fn extract_array<P: PyDual>(a: &PyAny) -> PyResult<(Vec<usize>, Vec<P::Data>)> {
+    let np = a.py().import("numpy")?;
+    let a = np.call_method1("asarray", (a, "O"))?;
+    let shape: Vec<usize> = a.getattr("shape")?.extract()?;
+    let data = a
+        .call_method0("ravel")?
+        .iter()?
+        .map(|x| P::extract_dual(x?))
+        .collect::<PyResult<_>>()?;
+    Ok((shape, data))
+}
+
+/// First entry of the arrays that is not a real number, which determines the
+/// type of the dual numbers.
+pub fn dual_entry<'py>(arrays: &[&'py PyAny]) -> PyResult<Option<&'py PyAny>> {
+    for a in arrays {
+        let np = a.py().import("numpy")?;
+        for x in np
+            .call_method1("asarray", (*a, "O"))?
+            .call_method0("ravel")?
+            .iter()?
+        {
+            let x = x?;
+            if extract_real(x).is_err() {
+                return Ok(Some(x));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// A Python dual number class and the num-dual type it wraps.
+pub trait PyDual:
+    Clone + From<Self::Data> + Into<Self::Data> + IntoPy<PyObject> + for<'a> FromPyObject<'a>
+{
+    type Data: DualNum<f64> + From<f64> + Copy + PartialEq;
+
+    /// Extract a dual number of this type or a real number.
+    fn extract_dual(obj: &PyAny) -> PyResult<Self::Data> {
+        if let Ok(x) = obj.extract::<Self>() {
+            return Ok(x.into());
+        }
+        Ok(Self::Data::from(extract_real(obj)?))
+    }
+
+    /// Extract a (nested) sequence of dual numbers or real numbers into a flat vector.
+    fn extract_flat(obj: &PyAny) -> PyResult<Vec<Self::Data>> {
+        if let Ok(x) = Self::extract_dual(obj) {
+            return Ok(vec![x]);
+        }
+        if obj.is_instance::<PyString>()? {
+            return Err(not_a_real(obj));
+        }
+        let mut res = Vec::new();
+        for x in obj.iter()? {
+            res.append(&mut Self::extract_flat(x?)?);
+        }
+        Ok(res)
+    }
+
+    /// First derivative of a dual number in a single direction, `None` for
+    /// real numbers and dual numbers with several directions.
+    fn first_derivative(_x: &Self::Data) -> Option<f64> {
+        None
+    }
+
+    /// Numpy array of the given shape containing the (dual) numbers.
+    fn to_array(py: Python, x: Vec<Self::Data>, shape: &[usize]) -> PyResult<PyObject> {
+        let x: Vec<PyObject> = x.into_iter().map(|x| Self::from(x).into_py(py)).collect();
+        let array = PyArray1::from_vec(py, x).reshape(shape)?;
+        object_array(py, array.to_object(py).into_ref(py))
+    }
+}
+
+impl PyDual for f64 {
+    type Data = f64;
+
+    fn to_array(py: Python, x: Vec<f64>, shape: &[usize]) -> PyResult<PyObject> {
+        Ok(PyArray1::from_vec(py, x).reshape(shape)?.to_object(py))
+    }
+}
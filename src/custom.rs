@@ -0,0 +1,149 @@
+//! Functions with user-defined derivatives.
+use crate::coerce::{extract_real, PyDual};
+use num_dual::DualNum;
+use numpy::PyArray1;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+#[pyclass(name = "CustomFunction")]
+/// Function of a single variable with user-defined derivatives.
+///
+/// Created by `custom_function`. When called with a (hyper) dual number,
+/// the function and its derivatives are evaluated on the real part
+/// and combined using the chain rule.
+pub struct PyCustomFunction {
+    f: PyObject,
+    derivatives: Vec<PyObject>,
+}
+
+impl PyCustomFunction {
+    fn call_scalar(&self, py: Python, x: &PyAny) -> PyResult<PyObject> {
+        if let Ok(x) = extract_real(x) {
+            return self.f.call1(py, (x,));
+        }
+        dispatch_dual!(
+            x,
+            apply(self, py, x),
+            Err(PyErr::new::<PyTypeError, _>(format!(
+                "expected a real or (hyper) dual number, got '{}'",
+                x.get_type().name()?
+            )))
+        )
+    }
+}
+
+/// Evaluate a custom function for a (hyper) dual number using the Taylor
+/// expansion around its real part.
+///
+/// Because the dual part `dx` is nilpotent, the expansion is exact once the
+/// powers of `dx` vanish.
+fn apply<P: PyDual>(func: &PyCustomFunction, py: Python, obj: &PyAny) -> PyResult<PyObject> {
+    let x = P::extract_dual(obj)?;
+    let re = x.re();
+    let zero = P::Data::from(0.0);
+    let dx = x - P::Data::from(re);
+    let mut res = P::Data::from(extract_real(func.f.call1(py, (re,))?.as_ref(py))?);
+    let mut dxk = P::Data::from(1.0);
+    let mut factorial = 1.0;
+    for (k, df) in func.derivatives.iter().enumerate() {
+        dxk = dxk * dx;
+        if dxk == zero {
+            return Ok(P::from(res).into_py(py));
+        }
+        factorial *= (k + 1) as f64;
+        let d = extract_real(df.call1(py, (re,))?.as_ref(py))?;
+        res = res + dxk * (d / factorial);
+    }
+    if dxk * dx != zero {
+        return Err(PyErr::new::<PyValueError, _>(format!(
+            "the derivative of order {} is required for '{}'",
+            func.derivatives.len() + 1,
+            obj.get_type().name()?
+        )));
+    }
+    Ok(P::from(res).into_py(py))
+}
+
+#[pymethods]
+impl PyCustomFunction {
+    #[call]
+    /// Evaluate the function.
+    ///
+    /// `x` can be a real number, any (hyper) dual number or an array
+    /// thereof.
+    pub fn __call__(&self, py: Python, x: &PyAny) -> PyResult<PyObject> {
+        if extract_real(x).is_ok() || x.iter().is_err() {
+            return self.call_scalar(py, x);
+        }
+        let np = py.import("numpy")?;
+        let x = np.call_method1("asarray", (x, "O"))?;
+        let shape: Vec<usize> = x.getattr("shape")?.extract()?;
+        let res = x
+            .call_method0("ravel")?
+            .iter()?
+            .map(|x| self.call_scalar(py, x?))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(PyArray1::from_vec(py, res)
+            .reshape(shape.as_slice())?
+            .to_object(py))
+    }
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(f, df, d2f=None, d3f=None, d4f=None)")]
+/// Function with user-defined derivatives.
+///
+/// Use this to evaluate functions that cannot be called with dual
+/// numbers (e.g. from scipy.special or external libraries) but whose
+/// derivatives are known.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     The function, called with a float.
+/// df : callable
+///     The first derivative of `f`.
+/// d2f : callable, optional
+///     The second derivative of `f`.
+/// d3f : callable, optional
+///     The third derivative of `f`.
+/// d4f : callable, optional
+///     The fourth derivative of `f`, only required for `Dual3Dual64`.
+///
+/// Returns
+/// -------
+/// CustomFunction
+///     Callable that accepts real numbers and all (hyper) dual numbers.
+///     Calling it with a dual number that requires derivatives of higher
+///     order than given raises a ValueError.
+///
+/// Examples
+///
+/// >>> from dualnum import custom_function, derive2
+/// >>> import math
+/// >>> f = custom_function(math.exp, math.exp, math.exp)
+/// >>> f(derive2(0.0)).second_derivative
+/// 1.0
+pub fn custom_function(
+    f: PyObject,
+    df: PyObject,
+    d2f: Option<PyObject>,
+    d3f: Option<PyObject>,
+    d4f: Option<PyObject>,
+) -> PyResult<PyCustomFunction> {
+    let mut derivatives = vec![df];
+    for (k, d) in vec![d2f, d3f, d4f].into_iter().enumerate() {
+        match d {
+            Some(_) if derivatives.len() < k + 1 => {
+                return Err(PyErr::new::<PyValueError, _>(format!(
+                    "d{}f can only be given together with d{}f!",
+                    k + 2,
+                    k + 1
+                )));
+            }
+            Some(d) => derivatives.push(d),
+            None => (),
+        }
+    }
+    Ok(PyCustomFunction { f, derivatives })
+}
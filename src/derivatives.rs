@@ -0,0 +1,347 @@
+//! Derivatives of Python functions evaluated with seeded dual numbers.
+//!
+//! The functions are called with the same structure as the variables (a
+//! scalar, a list or an array) and have to return a scalar, except for
+//! `jacobian` and `vector_at` which accept a sequence or array as result.
+use crate::coerce::{PyDual, Variables};
+use crate::dual::*;
+use crate::dual2::PyDual2_64;
+use crate::hyperdual::*;
+use num_dual::*;
+use pyo3::prelude::*;
+
+fn delta(i: usize, j: usize) -> f64 {
+    if i == j {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// The variables converted by `seed` in the structure of `x`.
+fn seeded<P: PyDual>(
+    py: Python,
+    x: &Variables,
+    seed: impl Fn(usize, f64) -> P::Data,
+) -> PyResult<PyObject> {
+    let args = x
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(k, &x)| P::from(seed(k, x)).into_py(py))
+        .collect();
+    x.wrap(py, args)
+}
+
+/// Call `f` with the variables converted by `seed` and extract the result.
+pub(crate) fn call<P: PyDual>(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    seed: impl Fn(usize, f64) -> P::Data,
+) -> PyResult<P::Data> {
+    P::extract_dual(f.call1((seeded::<P>(py, x, seed)?,))?)
+}
+
+/// Call `f` with the variables converted by `seed` and extract the result
+/// (a dual number or a sequence/array of dual numbers) into a flat vector.
+///
+/// Also returns the shape of the result, which is empty for a scalar.
+pub(crate) fn call_vec<P: PyDual>(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    seed: impl Fn(usize, f64) -> P::Data,
+) -> PyResult<(Vec<usize>, Vec<P::Data>)> {
+    let y = f.call1((seeded::<P>(py, x, seed)?,))?;
+    let values = P::extract_flat(y)?;
+    if P::extract_dual(y).is_ok() {
+        return Ok((vec![], values));
+    }
+    let shape = y
+        .getattr("shape")
+        .and_then(|s| s.extract::<Vec<usize>>())
+        .ok()
+        .filter(|s| s.iter().product::<usize>() == values.len())
+        .unwrap_or_else(|| vec![values.len()]);
+    Ok((shape, values))
+}
+
+/// Value of `f` for real variables.
+pub(crate) fn value(py: Python, f: &PyAny, x: &Variables) -> PyResult<f64> {
+    call::<f64>(py, f, x, |_, x| x)
+}
+
+/// Value of `f` for real variables given as a flat vector.
+pub(crate) fn value_at(py: Python, f: &PyAny, x: &Variables, values: &[f64]) -> PyResult<f64> {
+    call::<f64>(py, f, x, |k, _| values[k])
+}
+
+/// Values of a vector-valued `f` for real variables given as a flat vector.
+pub(crate) fn vector_at(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<Vec<f64>> {
+    Ok(call_vec::<f64>(py, f, x, |k, _| values[k])?.1)
+}
+
+/// Values and Jacobian (one row per value) of a vector-valued `f` for real
+/// variables given as a flat vector.
+///
+/// Uses one evaluation with vector dual numbers for up to 10 variables and one
+/// evaluation with dual numbers per variable otherwise.
+#[allow(clippy::type_complexity)]
+pub(crate) fn jacobian(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(Vec<f64>, Vec<Vec<f64>>)> {
+    let (_, y, jac) = jacobian_shaped(py, f, x, values)?;
+    Ok((y, jac))
+}
+
+/// Like `jacobian`, but also returns the shape of the result of `f`, which is
+/// empty if `f` returns a scalar.
+#[allow(clippy::type_complexity)]
+pub(crate) fn jacobian_shaped(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(Vec<usize>, Vec<f64>, Vec<Vec<f64>>)> {
+    let n = values.len();
+    match n {
+        2 => jacobian_vec::<PyDual64_2, 2>(py, f, x, values),
+        3 => jacobian_vec::<PyDual64_3, 3>(py, f, x, values),
+        4 => jacobian_vec::<PyDual64_4, 4>(py, f, x, values),
+        5 => jacobian_vec::<PyDual64_5, 5>(py, f, x, values),
+        6 => jacobian_vec::<PyDual64_6, 6>(py, f, x, values),
+        7 => jacobian_vec::<PyDual64_7, 7>(py, f, x, values),
+        8 => jacobian_vec::<PyDual64_8, 8>(py, f, x, values),
+        9 => jacobian_vec::<PyDual64_9, 9>(py, f, x, values),
+        10 => jacobian_vec::<PyDual64_10, 10>(py, f, x, values),
+        _ => {
+            let mut shape = Vec::new();
+            let mut y = Vec::new();
+            let mut jac = Vec::new();
+            for i in 0..n {
+                let (s, yi) = call_vec::<PyDual64>(py, f, x, |k, _| {
+                    Dual64::new_scalar(values[k], delta(i, k))
+                })?;
+                if i == 0 {
+                    shape = s;
+                    y = yi.iter().map(|y| y.re).collect();
+                    jac = vec![vec![0.0; n]; yi.len()];
+                }
+                for (row, yi) in jac.iter_mut().zip(&yi) {
+                    row[i] = yi.eps[0];
+                }
+            }
+            Ok((shape, y, jac))
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn jacobian_vec<P: PyDual<Data = DualVec64<N>>, const N: usize>(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(Vec<usize>, Vec<f64>, Vec<Vec<f64>>)> {
+    let (shape, y) = call_vec::<P>(py, f, x, |k, _| {
+        let mut eps = [0.0; N];
+        eps[k] = 1.0;
+        DualVec64::new(values[k], StaticVec::new_vec(eps))
+    })?;
+    Ok((
+        shape,
+        y.iter().map(|y| y.re).collect(),
+        y.iter().map(|y| y.eps.raw_array().to_vec()).collect(),
+    ))
+}
+
+/// Value and gradient of `f` using one evaluation with dual numbers per variable.
+pub(crate) fn gradient(py: Python, f: &PyAny, x: &Variables) -> PyResult<(f64, Vec<f64>)> {
+    let n = x.values().len();
+    if n == 0 {
+        return Ok((value(py, f, x)?, vec![]));
+    }
+    let mut v = 0.0;
+    let mut g = Vec::with_capacity(n);
+    for i in 0..n {
+        let y = call::<PyDual64>(py, f, x, |k, x| Dual64::new_scalar(x, delta(i, k)))?;
+        v = y.re;
+        g.push(y.eps[0]);
+    }
+    Ok((v, g))
+}
+
+/// Value, gradient and Hessian of `f` using one evaluation with hyper dual
+/// numbers per pair of variables.
+#[allow(clippy::type_complexity)]
+pub(crate) fn hessian(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+) -> PyResult<(f64, Vec<f64>, Vec<Vec<f64>>)> {
+    hessian_pairs(py, f, x, x.values())
+}
+
+/// Value, gradient and Hessian of `f` for real variables given as a flat vector.
+///
+/// Uses one evaluation with second order dual numbers for up to 5 variables and
+/// one evaluation with hyper dual numbers per pair of variables otherwise.
+#[allow(clippy::type_complexity)]
+pub(crate) fn hessian_at(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(f64, Vec<f64>, Vec<Vec<f64>>)> {
+    match values.len() {
+        1 => {
+            let y = call::<PyDual2_64>(py, f, x, |_, _| Dual2_64::new_scalar(values[0], 1.0, 0.0))?;
+            Ok((y.re, vec![y.v1[0]], vec![vec![y.v2[0]]]))
+        }
+        2 => hessian_vec::<PyHyperDual64_2, 2>(py, f, x, values),
+        3 => hessian_vec::<PyHyperDual64_3, 3>(py, f, x, values),
+        4 => hessian_vec::<PyHyperDual64_4, 4>(py, f, x, values),
+        5 => hessian_vec::<PyHyperDual64_5, 5>(py, f, x, values),
+        _ => hessian_pairs(py, f, x, values),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn hessian_vec<P: PyDual<Data = Dual2Vec64<N>>, const N: usize>(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(f64, Vec<f64>, Vec<Vec<f64>>)> {
+    let y = call::<P>(py, f, x, |k, _| {
+        let mut v1 = [0.0; N];
+        v1[k] = 1.0;
+        Dual2Vec64::new(
+            values[k],
+            StaticVec::new_vec(v1),
+            StaticMat::new([[0.0; N]; N]),
+        )
+    })?;
+    let h = y.v2.raw_data().iter().map(|row| row.to_vec()).collect();
+    Ok((y.re, y.v1.raw_array().to_vec(), h))
+}
+
+#[allow(clippy::type_complexity)]
+fn hessian_pairs(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+) -> PyResult<(f64, Vec<f64>, Vec<Vec<f64>>)> {
+    let n = values.len();
+    if n == 0 {
+        return Ok((value_at(py, f, x, values)?, vec![], vec![]));
+    }
+    let mut v = 0.0;
+    let mut g = vec![0.0; n];
+    let mut h = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in i..n {
+            let y = call::<PyHyperDual64>(py, f, x, |k, _| {
+                HyperDual64::new_scalar(values[k], delta(i, k), delta(j, k), 0.0)
+            })?;
+            v = y.re;
+            g[j] = y.eps2[0];
+            h[i][j] = y.eps1eps2[(0, 0)];
+            h[j][i] = h[i][j];
+        }
+    }
+    Ok((v, g, h))
+}
+
+/// Product of the Hessian of `f` with the vector `p` for real variables given
+/// as a flat vector.
+///
+/// Uses hyper dual numbers seeded with `p` in the first and unit vectors in
+/// the second direction. The unit vectors are processed in chunks of up to 5,
+/// i.e., `f` is evaluated once for up to 5 variables and ceil(n / 5) times
+/// otherwise.
+pub(crate) fn hessian_product(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+    p: &[f64],
+) -> PyResult<Vec<f64>> {
+    match values.len() {
+        0..=2 => hessian_product_vec::<PyHyperDual64_1_2, 2>(py, f, x, values, p),
+        3 => hessian_product_vec::<PyHyperDual64_1_3, 3>(py, f, x, values, p),
+        4 => hessian_product_vec::<PyHyperDual64_1_4, 4>(py, f, x, values, p),
+        _ => hessian_product_vec::<PyHyperDual64_1_5, 5>(py, f, x, values, p),
+    }
+}
+
+/// Hessian-vector product with one evaluation per chunk of `N` variables.
+fn hessian_product_vec<P: PyDual<Data = HyperDualVec64<1, N>>, const N: usize>(
+    py: Python,
+    f: &PyAny,
+    x: &Variables,
+    values: &[f64],
+    p: &[f64],
+) -> PyResult<Vec<f64>> {
+    let n = values.len();
+    let mut hp = Vec::with_capacity(n);
+    for start in (0..n).step_by(N) {
+        let y = call::<P>(py, f, x, |k, _| {
+            let mut eps2 = [0.0; N];
+            if (start..start + N).contains(&k) {
+                eps2[k - start] = 1.0;
+            }
+            HyperDualVec64::new(
+                values[k],
+                StaticVec::new_vec([p[k]]),
+                StaticVec::new_vec(eps2),
+                StaticMat::new([[0.0; N]]),
+            )
+        })?;
+        hp.extend(y.eps1eps2.raw_data()[0].iter().take(n - start));
+    }
+    Ok(hp)
+}
+
+/// Third partial derivatives of `f` (flattened, n x n x n) using one evaluation
+/// with hyper dual numbers of dual numbers per triple of variables.
+pub(crate) fn third_derivative(py: Python, f: &PyAny, x: &Variables) -> PyResult<Vec<f64>> {
+    let n = x.values().len();
+    let mut t = vec![0.0; n * n * n];
+    for i in 0..n {
+        for j in i..n {
+            for l in j..n {
+                let y = call::<PyHyperDualDual64>(py, f, x, |k, x| {
+                    HyperDual::new_scalar(
+                        Dual64::new_scalar(x, delta(l, k)),
+                        Dual64::from_re(delta(i, k)),
+                        Dual64::from_re(delta(j, k)),
+                        Dual64::from_re(0.0),
+                    )
+                })?;
+                let d = y.eps1eps2[(0, 0)].eps[0];
+                for &(a, b, c) in &[
+                    (i, j, l),
+                    (i, l, j),
+                    (j, i, l),
+                    (j, l, i),
+                    (l, i, j),
+                    (l, j, i),
+                ] {
+                    t[(a * n + b) * n + c] = d;
+                }
+            }
+        }
+    }
+    Ok(t)
+}
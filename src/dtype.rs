@@ -0,0 +1,408 @@
+//! Numpy dtypes for dual numbers.
+//!
+//! The dtypes store the real and derivative parts of every element
+//! contiguously (as C structs of 64-bit-floats) inside the numpy array.
+//! Elements are converted from and to the Python dual number classes on
+//! access, casts from float arrays are supported, and arithmetic and
+//! mathematical ufuncs are evaluated in Rust loops on top of num-dual. The
+//! scalar types of the dtypes (`dual64_dtype.type`, ...) are subclasses of
+//! `numpy.generic` that only identify the dtypes.
+//!
+//! Examples
+//!
+//! >>> import numpy as np
+//! >>> from dualnum import Dual64, dual64_dtype
+//! >>> x = np.array([1.0, 4.0]).astype(dual64_dtype)
+//! >>> x[1] = Dual64(4.0, 1.0)
+//! >>> np.sqrt(x)[1]
+//! 2 + [0.25]ε
+use crate::coerce::extract_real;
+use crate::dual::PyDual64;
+use crate::dual2::PyDual2_64;
+use crate::dual3::PyDual3_64;
+use crate::hyperdual::PyHyperDual64;
+use num_dual::*;
+use numpy::npyffi::{
+    npy_intp, NpyTypes, PyArray_ArrFuncs, PyArray_Descr, PyUFuncObject, NPY_NEEDS_PYAPI,
+    NPY_SCALARKIND, NPY_TYPES, NPY_USE_GETITEM, NPY_USE_SETITEM, PY_ARRAY_API, PY_UFUNC_API,
+};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyType};
+use pyo3::{ffi, PyClass};
+use std::mem::{align_of, size_of};
+use std::os::raw::{c_char, c_int, c_uchar, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Dual number with a C-compatible memory layout that is stored in numpy arrays.
+trait DType: Copy + 'static {
+    type Data: DualNum<f64> + From<f64> + Copy;
+    type Py: PyClass + Clone + From<Self::Data> + Into<Self::Data> + IntoPy<PyObject>;
+
+    /// Name of the dtype in the Python module.
+    const NAME: &'static str;
+    /// Character code of the dtype.
+    const TYPE_CHAR: u8;
+
+    /// Type number assigned by numpy.
+    fn type_num() -> &'static AtomicI32;
+    fn from_data(x: Self::Data) -> Self;
+    fn to_data(self) -> Self::Data;
+
+    /// Real and derivative parts.
+    fn parts(&self) -> &[f64] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const Self as *const f64,
+                size_of::<Self>() / size_of::<f64>(),
+            )
+        }
+    }
+
+    fn parts_mut(&mut self) -> &mut [f64] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self as *mut Self as *mut f64,
+                size_of::<Self>() / size_of::<f64>(),
+            )
+        }
+    }
+
+    fn extract(obj: &PyAny) -> PyResult<Self> {
+        if let Ok(x) = obj.extract::<Self::Py>() {
+            return Ok(Self::from_data(x.into()));
+        }
+        Ok(Self::from_data(Self::Data::from(extract_real(obj)?)))
+    }
+}
+
+macro_rules! impl_dtype {
+    ($item:ident, $data_type:ty, $py_type_name:ty, $name:literal, $type_char:literal, [$($part:ident),+], $from_data:expr, $to_data:expr) => {
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        struct $item {
+            $($part: f64,)+
+        }
+
+        impl DType for $item {
+            type Data = $data_type;
+            type Py = $py_type_name;
+
+            const NAME: &'static str = $name;
+            const TYPE_CHAR: u8 = $type_char;
+
+            fn type_num() -> &'static AtomicI32 {
+                static TYPE_NUM: AtomicI32 = AtomicI32::new(-1);
+                &TYPE_NUM
+            }
+
+            fn from_data(x: Self::Data) -> Self {
+                let ($($part),+) = $from_data(x);
+                Self { $($part),+ }
+            }
+
+            fn to_data(self) -> Self::Data {
+                $to_data($(self.$part),+)
+            }
+        }
+    };
+}
+
+impl_dtype!(
+    Dual64Item,
+    Dual64,
+    PyDual64,
+    "dual64_dtype",
+    b'j',
+    [re, eps],
+    |x: Dual64| (x.re, x.eps[0]),
+    Dual64::new_scalar
+);
+impl_dtype!(
+    HyperDual64Item,
+    HyperDual64,
+    PyHyperDual64,
+    "hyperdual64_dtype",
+    b'J',
+    [re, eps1, eps2, eps1eps2],
+    |x: HyperDual64| (x.re, x.eps1[0], x.eps2[0], x.eps1eps2[(0, 0)]),
+    HyperDual64::new_scalar
+);
+impl_dtype!(
+    Dual2_64Item,
+    Dual2_64,
+    PyDual2_64,
+    "dual2_64_dtype",
+    b'k',
+    [re, v1, v2],
+    |x: Dual2_64| (x.re, x.v1[0], x.v2[0]),
+    Dual2_64::new_scalar
+);
+impl_dtype!(
+    Dual3_64Item,
+    Dual3_64,
+    PyDual3_64,
+    "dual3_64_dtype",
+    b'K',
+    [re, v1, v2, v3],
+    |x: Dual3_64| (x.re, x.v1, x.v2, x.v3),
+    Dual3_64::new
+);
+
+unsafe extern "C" fn getitem<T: DType>(data: *mut c_void, _arr: *mut c_void) -> *mut ffi::PyObject {
+    let py = Python::assume_gil_acquired();
+    let x = (data as *const T).read_unaligned();
+    T::Py::from(x.to_data()).into_py(py).into_ptr()
+}
+
+unsafe extern "C" fn setitem<T: DType>(
+    obj: *mut ffi::PyObject,
+    data: *mut c_void,
+    _arr: *mut c_void,
+) -> c_int {
+    let py = Python::assume_gil_acquired();
+    match T::extract(py.from_borrowed_ptr(obj)) {
+        Ok(x) => {
+            (data as *mut T).write_unaligned(x);
+            0
+        }
+        Err(e) => {
+            e.restore(py);
+            -1
+        }
+    }
+}
+
+unsafe extern "C" fn copyswapn<T: DType>(
+    dst: *mut c_void,
+    dstride: npy_intp,
+    src: *mut c_void,
+    sstride: npy_intp,
+    n: npy_intp,
+    swap: c_int,
+    _arr: *mut c_void,
+) {
+    if src.is_null() {
+        return;
+    }
+    for i in 0..n {
+        let mut x = ((src as *const u8).offset(i * sstride) as *const T).read_unaligned();
+        if swap != 0 {
+            x.parts_mut()
+                .iter_mut()
+                .for_each(|p| *p = f64::from_bits(p.to_bits().swap_bytes()));
+        }
+        ((dst as *mut u8).offset(i * dstride) as *mut T).write_unaligned(x);
+    }
+}
+
+unsafe extern "C" fn copyswap<T: DType>(
+    dst: *mut c_void,
+    src: *mut c_void,
+    swap: c_int,
+    arr: *mut c_void,
+) {
+    copyswapn::<T>(dst, 0, src, 0, 1, swap, arr)
+}
+
+unsafe extern "C" fn nonzero<T: DType>(data: *mut c_void, _arr: *mut c_void) -> c_uchar {
+    let x = (data as *const T).read_unaligned();
+    x.parts().iter().any(|&p| p != 0.0) as c_uchar
+}
+
+type CastFunc = unsafe extern "C" fn(*mut c_void, *mut c_void, npy_intp, *mut c_void, *mut c_void);
+
+unsafe extern "C" fn cast<F: Copy + Into<f64>, T: DType>(
+    from: *mut c_void,
+    to: *mut c_void,
+    n: npy_intp,
+    _fromarr: *mut c_void,
+    _toarr: *mut c_void,
+) {
+    for i in 0..n {
+        let x = (from as *const F).offset(i).read_unaligned();
+        (to as *mut T)
+            .offset(i)
+            .write_unaligned(T::from_data(T::Data::from(x.into())));
+    }
+}
+
+unsafe extern "C" fn unary_loop<T: DType>(
+    args: *mut *mut c_char,
+    dimensions: *mut npy_intp,
+    steps: *mut npy_intp,
+    data: *mut c_void,
+) {
+    let f: fn(T::Data) -> T::Data = std::mem::transmute(data);
+    let (input, output) = (*args, *args.offset(1));
+    let (s_in, s_out) = (*steps, *steps.offset(1));
+    for i in 0..*dimensions {
+        let x = (input.offset(i * s_in) as *const T).read_unaligned();
+        (output.offset(i * s_out) as *mut T).write_unaligned(T::from_data(f(x.to_data())));
+    }
+}
+
+unsafe extern "C" fn binary_loop<T: DType>(
+    args: *mut *mut c_char,
+    dimensions: *mut npy_intp,
+    steps: *mut npy_intp,
+    data: *mut c_void,
+) {
+    let f: fn(T::Data, T::Data) -> T::Data = std::mem::transmute(data);
+    let (in1, in2, output) = (*args, *args.offset(1), *args.offset(2));
+    let (s_in1, s_in2, s_out) = (*steps, *steps.offset(1), *steps.offset(2));
+    for i in 0..*dimensions {
+        let x1 = (in1.offset(i * s_in1) as *const T).read_unaligned();
+        let x2 = (in2.offset(i * s_in2) as *const T).read_unaligned();
+        let y = f(x1.to_data(), x2.to_data());
+        (output.offset(i * s_out) as *mut T).write_unaligned(T::from_data(y));
+    }
+}
+
+fn register_loop<T: DType>(
+    py: Python,
+    name: &str,
+    function: numpy::npyffi::PyUFuncGenericFunction,
+    nargs: usize,
+    data: *mut c_void,
+) -> PyResult<()> {
+    let ufunc = py.import("numpy")?.getattr(name)?;
+    let mut arg_types = vec![T::type_num().load(Ordering::Relaxed); nargs];
+    let res = unsafe {
+        PY_UFUNC_API.PyUFunc_RegisterLoopForType(
+            ufunc.as_ptr() as *mut PyUFuncObject,
+            arg_types[0],
+            function,
+            arg_types.as_mut_ptr(),
+            data,
+        )
+    };
+    if res < 0 {
+        return Err(PyErr::fetch(py));
+    }
+    Ok(())
+}
+
+/// Scalar type of the dtype.
+///
+/// Numpy requires the scalar type of user-defined dtypes to be a subclass of
+/// `numpy.generic`. The elements of arrays are nevertheless returned as
+/// instances of the dual number classes.
+fn scalar_type<'py, T: DType>(py: Python<'py>) -> PyResult<&'py PyAny> {
+    let generic = py.import("numpy")?.getattr("generic")?;
+    let dict = PyDict::new(py);
+    dict.set_item("__module__", "dualnum")?;
+    dict.set_item("__doc__", format!("Scalar type of `{}`.", T::NAME))?;
+    let name = T::NAME.trim_end_matches("_dtype");
+    py.get_type::<PyType>().call1((name, (generic,), dict))
+}
+
+fn register<T: DType>(py: Python, m: &PyModule) -> PyResult<()> {
+    let typeobj = scalar_type::<T>(py)?.to_object(py);
+    let descr = unsafe {
+        let mut f: PyArray_ArrFuncs = std::mem::zeroed();
+        PY_ARRAY_API.PyArray_InitArrFuncs(&mut f);
+        f.getitem = Some(getitem::<T>);
+        f.setitem = Some(setitem::<T>);
+        f.copyswapn = Some(copyswapn::<T>);
+        f.copyswap = Some(copyswap::<T>);
+        f.nonzero = Some(nonzero::<T>);
+
+        // numpy keeps pointers to the descriptor, the functions and the
+        // scalar type for the lifetime of the interpreter.
+        let mut descr: PyArray_Descr = std::mem::zeroed();
+        descr.ob_base.ob_refcnt = 1;
+        descr.ob_base.ob_type = PY_ARRAY_API.get_type_object(NpyTypes::PyArrayDescr_Type);
+        descr.typeobj = typeobj.into_ptr() as *mut ffi::PyTypeObject;
+        descr.kind = b'V' as c_char;
+        descr.type_ = T::TYPE_CHAR as c_char;
+        descr.byteorder = b'=' as c_char;
+        descr.flags = NPY_NEEDS_PYAPI | NPY_USE_GETITEM | NPY_USE_SETITEM;
+        descr.elsize = size_of::<T>() as c_int;
+        descr.alignment = align_of::<T>() as c_int;
+        descr.f = Box::leak(Box::new(f));
+        descr.hash = -1;
+        let descr: &mut PyArray_Descr = Box::leak(Box::new(descr));
+        if PY_ARRAY_API.PyArray_RegisterDataType(descr) < 0 {
+            return Err(PyErr::fetch(py));
+        }
+        descr
+    };
+    let type_num = descr.type_num;
+    T::type_num().store(type_num, Ordering::Relaxed);
+
+    // casts from floating point arrays
+    let casts: [(NPY_TYPES, CastFunc); 2] = [
+        (NPY_TYPES::NPY_DOUBLE, cast::<f64, T>),
+        (NPY_TYPES::NPY_FLOAT, cast::<f32, T>),
+    ];
+    unsafe {
+        for &(from, cast) in casts.iter() {
+            let from = PY_ARRAY_API.PyArray_DescrFromType(from as c_int);
+            if PY_ARRAY_API.PyArray_RegisterCastFunc(from, type_num, Some(cast)) < 0
+                || PY_ARRAY_API.PyArray_RegisterCanCast(
+                    from,
+                    type_num,
+                    NPY_SCALARKIND::NPY_NOSCALAR,
+                ) < 0
+            {
+                return Err(PyErr::fetch(py));
+            }
+        }
+    }
+
+    let unary: [(&str, fn(T::Data) -> T::Data); 23] = [
+        ("negative", |x| -x),
+        ("reciprocal", |x| x.recip()),
+        ("sqrt", |x| x.sqrt()),
+        ("cbrt", |x| x.cbrt()),
+        ("exp", |x| x.exp()),
+        ("exp2", |x| x.exp2()),
+        ("expm1", |x| x.exp_m1()),
+        ("log", |x| x.ln()),
+        ("log2", |x| x.log2()),
+        ("log10", |x| x.log10()),
+        ("log1p", |x| x.ln_1p()),
+        ("sin", |x| x.sin()),
+        ("cos", |x| x.cos()),
+        ("tan", |x| x.tan()),
+        ("arcsin", |x| x.asin()),
+        ("arccos", |x| x.acos()),
+        ("arctan", |x| x.atan()),
+        ("sinh", |x| x.sinh()),
+        ("cosh", |x| x.cosh()),
+        ("tanh", |x| x.tanh()),
+        ("arcsinh", |x| x.asinh()),
+        ("arccosh", |x| x.acosh()),
+        ("arctanh", |x| x.atanh()),
+    ];
+    for (name, f) in unary.iter() {
+        register_loop::<T>(py, name, Some(unary_loop::<T>), 2, *f as *mut c_void)?;
+    }
+
+    let binary: [(&str, fn(T::Data, T::Data) -> T::Data); 4] = [
+        ("add", |x, y| x + y),
+        ("subtract", |x, y| x - y),
+        ("multiply", |x, y| x * y),
+        ("true_divide", |x, y| x / y),
+    ];
+    for (name, f) in binary.iter() {
+        register_loop::<T>(py, name, Some(binary_loop::<T>), 3, *f as *mut c_void)?;
+    }
+
+    let dtype = unsafe {
+        PyObject::from_owned_ptr(
+            py,
+            PY_ARRAY_API.PyArray_DescrFromType(type_num) as *mut ffi::PyObject,
+        )
+    };
+    m.add(T::NAME, dtype)
+}
+
+/// Register the numpy dtypes of all scalar dual numbers and add them to the module.
+pub fn register_dtypes(py: Python, m: &PyModule) -> PyResult<()> {
+    register::<Dual64Item>(py, m)?;
+    register::<HyperDual64Item>(py, m)?;
+    register::<Dual2_64Item>(py, m)?;
+    register::<Dual3_64Item>(py, m)?;
+    Ok(())
+}
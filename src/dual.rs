@@ -1,4 +1,6 @@
+use crate::coerce::{extract_real, FromPyReal, Variables};
 use num_dual::*;
+use numpy::PyArray1;
 use pyo3::exceptions::PyTypeError;
 use pyo3::number::PyNumberProtocol;
 use pyo3::prelude::*;
@@ -37,10 +39,10 @@ pub struct PyDual64 {
 #[pymethods]
 impl PyDual64 {
     #[new]
-    pub fn new(re: f64, eps: f64) -> Self {
-        Self {
-            _data: Dual64::new_scalar(re, eps),
-        }
+    pub fn new(re: &PyAny, eps: &PyAny) -> PyResult<Self> {
+        Ok(Self {
+            _data: Dual64::new_scalar(extract_real(re)?, extract_real(eps)?),
+        })
     }
 
     #[getter]
@@ -50,7 +52,16 @@ impl PyDual64 {
     }
 }
 
-impl_dual_num!(PyDual64, Dual64, f64);
+impl_dual_num!(PyDual64, Dual64, f64, |x| Some(x.eps[0]));
+
+impl FromPyReal for PyDual64 {
+    fn from_py_real(obj: &PyAny) -> PyResult<Self> {
+        if let Ok(x) = obj.extract::<Self>() {
+            return Ok(x);
+        };
+        Ok(Dual64::from_re(extract_real(obj)?).into())
+    }
+}
 
 macro_rules! impl_dual_n {
     ($py_type_name:ident, $n:literal) => {
@@ -75,29 +86,66 @@ macro_rules! impl_dual_n {
             pub fn get_first_derivative(&self) -> [f64; $n] {
                 *self._data.eps.raw_array()
             }
+
+            #[getter]
+            /// Dual part as numpy array.
+            pub fn get_first_derivative_array<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+                PyArray1::from_slice(py, self._data.eps.raw_array())
+            }
         }
 
         impl_dual_num!($py_type_name, DualVec64<$n>, f64);
     };
 }
 
+/// Seed the entries of `x` selected by `seed` as independent variables of vector dual numbers.
+pub(crate) fn seed_vec<P: IntoPy<PyObject>, const N: usize>(
+    py: Python,
+    x: &[f64],
+    seed: &[bool],
+    new: impl Fn(f64, [f64; N]) -> P,
+) -> Vec<PyObject> {
+    let mut i = 0;
+    x.iter()
+        .zip(seed)
+        .map(|(&x, &s)| {
+            let mut eps = [0.0; N];
+            if s {
+                eps[i] = 1.0;
+                i += 1;
+            }
+            new(x, eps).into_py(py)
+        })
+        .collect()
+}
+
 macro_rules! impl_derive {
     ([$(($py_type_name:ident, $n:literal)),+]) => {
         #[pyfunction]
-        #[pyo3(text_signature = "(x)")]
-        pub fn derive1(x: &PyAny) -> PyResult<PyObject> {
+        #[pyo3(text_signature = "(x, seed=None)")]
+        /// Seed the given value(s) as independent variables of dual numbers.
+        ///
+        /// x can be a scalar, a sequence or an array of any shape. The result has
+        /// the same structure. If seed (a boolean array of the same shape as x) is
+        /// given, only the selected entries are seeded.
+        pub fn derive1(x: &PyAny, seed: Option<&PyAny>) -> PyResult<PyObject> {
             Python::with_gil(|py| {
-                if let Ok(x) = x.extract::<f64>() {
-                    return Ok(PyCell::new(py, PyDual64::from(Dual64::from_re(x).derive()))?.to_object(py));
+                let x = Variables::extract(x)?;
+                let seed = x.seed(seed)?;
+                let values = x.values();
+                let res = match seed.iter().filter(|&&s| s).count() {
+                    1 => seed_vec(py, values, &seed, |re, [eps]: [f64; 1]| {
+                        PyDual64::from(Dual64::new_scalar(re, eps))
+                    }),
+                    $($n => seed_vec(py, values, &seed, $py_type_name::new),)+
+                    n => {
+                        return Err(PyErr::new::<PyTypeError, _>(format!(
+                            "derive1 is not implemented for {} variables (at most 10)! Use seed to select the entries to differentiate.",
+                            n
+                        )))
+                    }
                 };
-                $(
-                    if let Ok(x) = x.extract::<[f64; $n]>() {
-                        let arr = StaticVec::new_vec(x).map(DualVec64::from).derive();
-                        let py_vec: Result<Vec<&PyCell<$py_type_name>>, _> = arr.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name::from(i))).collect();
-                        return Ok(py_vec?.to_object(py));
-                    };
-                )+
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                x.wrap(py, res)
             })
         }
         $(impl_dual_n!($py_type_name, $n);)+
@@ -1,6 +1,6 @@
+use crate::coerce::{extract_real, FromPyReal};
 use crate::dual::PyDual64;
 use num_dual::*;
-use pyo3::exceptions::PyTypeError;
 use pyo3::number::PyNumberProtocol;
 use pyo3::prelude::*;
 
@@ -14,8 +14,8 @@ pub struct PyDual2_64 {
 #[pymethods]
 impl PyDual2_64 {
     #[new]
-    fn new(eps: f64, v1: f64, v2: f64) -> Self {
-        Dual2::new_scalar(eps, v1, v2).into()
+    fn new(eps: &PyAny, v1: &PyAny, v2: &PyAny) -> PyResult<Self> {
+        Ok(Dual2::new_scalar(extract_real(eps)?, extract_real(v1)?, extract_real(v2)?).into())
     }
 
     #[getter]
@@ -31,7 +31,7 @@ impl PyDual2_64 {
     }
 }
 
-impl_dual_num!(PyDual2_64, Dual2_64, f64);
+impl_dual_num!(PyDual2_64, Dual2_64, f64, |x| Some(x.v1[0]));
 
 #[pyclass(name = "Dual2Dual64")]
 #[derive(Clone)]
@@ -43,8 +43,11 @@ pub struct PyDual2Dual64 {
 #[pymethods]
 impl PyDual2Dual64 {
     #[new]
-    pub fn new(v0: PyDual64, v1: PyDual64, v2: PyDual64) -> Self {
-        Dual2::new_scalar(v0._data, v1._data, v2._data).into()
+    pub fn new(v0: &PyAny, v1: &PyAny, v2: &PyAny) -> PyResult<Self> {
+        let v0 = PyDual64::from_py_real(v0)?;
+        let v1 = PyDual64::from_py_real(v1)?;
+        let v2 = PyDual64::from_py_real(v2)?;
+        Ok(Dual2::new_scalar(v0._data, v1._data, v2._data).into())
     }
 
     #[getter]
@@ -1,3 +1,4 @@
+use crate::coerce::{extract_real, FromPyReal, Variables};
 use crate::dual::PyDual64;
 use num_dual::*;
 use pyo3::exceptions::PyTypeError;
@@ -14,8 +15,14 @@ pub struct PyDual3_64 {
 #[pymethods]
 impl PyDual3_64 {
     #[new]
-    fn new(eps: f64, v1: f64, v2: f64, v3: f64) -> Self {
-        Dual3::new(eps, v1, v2, v3).into()
+    fn new(eps: &PyAny, v1: &PyAny, v2: &PyAny, v3: &PyAny) -> PyResult<Self> {
+        Ok(Dual3::new(
+            extract_real(eps)?,
+            extract_real(v1)?,
+            extract_real(v2)?,
+            extract_real(v3)?,
+        )
+        .into())
     }
 
     #[getter]
@@ -37,7 +44,7 @@ impl PyDual3_64 {
     }
 }
 
-impl_dual_num!(PyDual3_64, Dual3_64, f64);
+impl_dual_num!(PyDual3_64, Dual3_64, f64, |x| Some(x.v1));
 
 #[pyclass(name = "Dual3Dual64")]
 #[derive(Clone)]
@@ -49,8 +56,12 @@ pub struct PyDual3Dual64 {
 #[pymethods]
 impl PyDual3Dual64 {
     #[new]
-    pub fn new(v0: PyDual64, v1: PyDual64, v2: PyDual64, v3: PyDual64) -> Self {
-        Dual3::new(v0._data, v1._data, v2._data, v3._data).into()
+    pub fn new(v0: &PyAny, v1: &PyAny, v2: &PyAny, v3: &PyAny) -> PyResult<Self> {
+        let v0 = PyDual64::from_py_real(v0)?;
+        let v1 = PyDual64::from_py_real(v1)?;
+        let v2 = PyDual64::from_py_real(v2)?;
+        let v3 = PyDual64::from_py_real(v3)?;
+        Ok(Dual3::new(v0._data, v1._data, v2._data, v3._data).into())
     }
 
     #[getter]
@@ -75,20 +86,41 @@ impl PyDual3Dual64 {
 impl_dual_num!(PyDual3Dual64, Dual3<Dual64, f64>, PyDual64);
 
 #[pyfunction]
-#[pyo3(text_signature = "(x)")]
-fn derive3(x: &PyAny) -> PyResult<PyObject> {
+#[pyo3(text_signature = "(x, seed=None)")]
+/// Seed the given value as independent variable of third order dual numbers.
+///
+/// x can be a scalar, a sequence or an array of any shape. The result has the
+/// same structure. As for derive1 and derive2, the seeded entries are
+/// independent variables. Third order dual numbers only support a single
+/// variable, so exactly one entry has to be seeded: x has to contain a single
+/// value or seed (a boolean array of the same shape as x) has to select one
+/// entry.
+fn derive3(x: &PyAny, seed: Option<&PyAny>) -> PyResult<PyObject> {
     Python::with_gil(|py| {
-        if let Ok(x) = x.extract::<f64>() {
-            return Ok(
-                PyCell::new(py, PyDual3_64::from(Dual3_64::from_re(x).derive()))?.to_object(py),
-            );
-        };
         if let Ok(x) = x.extract::<PyDual64>() {
             return Ok(
                 PyCell::new(py, PyDual3Dual64::from(Dual3::from_re(x._data).derive()))?
                     .to_object(py),
             );
         };
-        Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+        let x = Variables::extract(x)?;
+        let seed = x.seed(seed)?;
+        let n = seed.iter().filter(|&&s| s).count();
+        if n != 1 {
+            return Err(PyErr::new::<PyTypeError, _>(format!(
+                "derive3 is only implemented for a single variable, got {} variables! Use seed to select one entry.",
+                n
+            )));
+        }
+        let res = x
+            .values()
+            .iter()
+            .zip(seed)
+            .map(|(&x, s)| {
+                let v1 = if s { 1.0 } else { 0.0 };
+                PyDual3_64::from(Dual3_64::new(x, v1, 0.0, 0.0)).into_py(py)
+            })
+            .collect();
+        x.wrap(py, res)
     })
 }
@@ -0,0 +1,273 @@
+use crate::coerce::{extract_real, Variables};
+use crate::dual::PyDual64;
+use crate::dual2::PyDual2_64;
+use num_dual::*;
+use numpy::{PyArray1, PyArrayDyn, PyReadonlyArrayDyn};
+use pyo3::exceptions::PyValueError;
+use pyo3::number::PyNumberProtocol;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// Maximum number of parts of the dual numbers that can be stored in a dual array.
+const MAX_PARTS: usize = 3;
+
+/// Conversion between a dual number and its real and derivative parts.
+pub trait DualParts: Copy {
+    /// Number of parts.
+    const PARTS: usize;
+    fn from_parts(parts: &[f64]) -> Self;
+    fn to_parts(&self) -> [f64; MAX_PARTS];
+}
+
+impl DualParts for Dual64 {
+    const PARTS: usize = 2;
+
+    fn from_parts(parts: &[f64]) -> Self {
+        Dual64::new_scalar(parts[0], parts[1])
+    }
+
+    fn to_parts(&self) -> [f64; MAX_PARTS] {
+        [self.re, self.eps[0], 0.0]
+    }
+}
+
+impl DualParts for Dual2_64 {
+    const PARTS: usize = 3;
+
+    fn from_parts(parts: &[f64]) -> Self {
+        Dual2_64::new_scalar(parts[0], parts[1], parts[2])
+    }
+
+    fn to_parts(&self) -> [f64; MAX_PARTS] {
+        [self.re, self.v1[0], self.v2[0]]
+    }
+}
+
+/// Array of dual numbers stored as one contiguous buffer per part.
+#[derive(Clone)]
+pub struct DualArray<T> {
+    shape: Vec<usize>,
+    parts: Vec<Vec<f64>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: DualParts> DualArray<T> {
+    fn from_fn(shape: Vec<usize>, f: impl Fn(usize) -> T) -> Self {
+        let len = shape.iter().product();
+        let mut parts: Vec<_> = (0..T::PARTS).map(|_| Vec::with_capacity(len)).collect();
+        for i in 0..len {
+            for (p, &x) in parts.iter_mut().zip(f(i).to_parts().iter()) {
+                p.push(x);
+            }
+        }
+        Self {
+            shape,
+            parts,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Dual array from real parts, setting all other parts to zero.
+    pub fn from_re(shape: Vec<usize>, re: Vec<f64>) -> Self {
+        let mut parts = vec![vec![0.0; re.len()]; T::PARTS];
+        parts[0] = re;
+        Self {
+            shape,
+            parts,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Dual array from real parts, setting all first derivatives to one.
+    pub fn derive(shape: Vec<usize>, re: Vec<f64>) -> Self {
+        let mut res = Self::from_re(shape, re);
+        res.parts[1].iter_mut().for_each(|x| *x = 1.0);
+        res
+    }
+
+    pub fn len(&self) -> usize {
+        self.parts[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The dual number at the given (flat) index.
+    pub fn get(&self, i: usize) -> T {
+        let mut parts = [0.0; MAX_PARTS];
+        for (x, p) in parts.iter_mut().zip(self.parts.iter()) {
+            *x = p[i];
+        }
+        T::from_parts(&parts)
+    }
+
+    /// Broadcast the arrays against each other following numpy's rules.
+    ///
+    /// Arrays of the same shape are borrowed and only copied if they need
+    /// to be broadcast.
+    fn broadcast<'a>(py: Python, arrays: &[&'a Self]) -> PyResult<Vec<Cow<'a, Self>>> {
+        let shape = &arrays[0].shape;
+        if arrays.iter().all(|a| &a.shape == shape) {
+            return Ok(arrays.iter().map(|&a| Cow::Borrowed(a)).collect());
+        }
+        let mut parts = Vec::with_capacity(arrays.len() * T::PARTS);
+        for a in arrays {
+            for k in 0..T::PARTS {
+                parts.push(a.part(py, k)?);
+            }
+        }
+        let np = py.import("numpy")?;
+        let parts: Vec<&PyAny> = np
+            .call_method1("broadcast_arrays", PyTuple::new(py, parts))?
+            .extract()?;
+        let mut res = Vec::with_capacity(arrays.len());
+        for a in parts.chunks(T::PARTS) {
+            let shape: Vec<usize> = a[0].getattr("shape")?.extract()?;
+            let parts = a
+                .iter()
+                .map(|p| {
+                    let p: PyReadonlyArrayDyn<f64> = p.extract()?;
+                    Ok(p.as_array().iter().copied().collect())
+                })
+                .collect::<PyResult<_>>()?;
+            res.push(Cow::Owned(Self {
+                shape,
+                parts,
+                _phantom: PhantomData,
+            }));
+        }
+        Ok(res)
+    }
+
+    /// Apply a function to every element.
+    pub fn map(&self, f: impl Fn(T) -> T) -> Self {
+        Self::from_fn(self.shape.clone(), |i| f(self.get(i)))
+    }
+
+    /// Apply a function elementwise to two arrays broadcast against each other.
+    pub fn zip(&self, py: Python, other: &Self, f: impl Fn(T, T) -> T) -> PyResult<Self> {
+        let b = Self::broadcast(py, &[self, other])?;
+        Ok(Self::from_fn(b[0].shape.clone(), |i| f(b[0].get(i), b[1].get(i))))
+    }
+
+    /// Apply a function elementwise to three arrays broadcast against each other.
+    pub fn zip3(&self, py: Python, a: &Self, b: &Self, f: impl Fn(T, T, T) -> T) -> PyResult<Self> {
+        let b = Self::broadcast(py, &[self, a, b])?;
+        Ok(Self::from_fn(b[0].shape.clone(), |i| {
+            f(b[0].get(i), b[1].get(i), b[2].get(i))
+        }))
+    }
+
+    /// Numpy array of the given part.
+    pub fn part<'py>(&self, py: Python<'py>, k: usize) -> PyResult<&'py PyArrayDyn<f64>> {
+        PyArray1::from_slice(py, &self.parts[k]).reshape(self.shape.as_slice())
+    }
+}
+
+#[pyclass(name = "DualArray64")]
+#[derive(Clone)]
+/// Array of dual numbers using 64-bit-floats.
+///
+/// Real and dual parts are stored as contiguous arrays and all
+/// operations are evaluated elementwise in Rust. Binary operations,
+/// also with numpy arrays, broadcast their operands like numpy.
+///
+/// Examples
+///
+/// >>> from dualnum import DualArray64
+/// >>> import numpy as np
+/// >>> x = DualArray64.derive(np.linspace(1.0, 2.0, 5))
+/// >>> fx = x * x + np.sqrt(x)
+/// >>> fx.first_derivative
+/// array([2.5       , 2.9472136 , 3.40824829, 3.87796447, 4.35355339])
+pub struct PyDualArray64 {
+    pub _data: DualArray<Dual64>,
+}
+
+#[pymethods]
+impl PyDualArray64 {
+    #[new]
+    pub fn new(re: &PyAny, eps: &PyAny) -> PyResult<Self> {
+        let re = Variables::extract(re)?;
+        let eps = Variables::extract(eps)?;
+        let shape = re.shape();
+        if eps.shape() != shape {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "real and dual parts have to be of the same shape, got {:?} and {:?}!",
+                shape,
+                eps.shape()
+            )));
+        }
+        let (re, eps) = (re.into_values(), eps.into_values());
+        Ok(Self {
+            _data: DualArray {
+                shape,
+                parts: vec![re, eps],
+                _phantom: PhantomData,
+            },
+        })
+    }
+
+    #[getter]
+    /// Dual part.
+    pub fn get_first_derivative<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArrayDyn<f64>> {
+        self._data.part(py, 1)
+    }
+}
+
+impl_dual_array!(PyDualArray64, "DualArray64", Dual64, PyDual64);
+
+#[pyclass(name = "Dual2Array64")]
+#[derive(Clone)]
+/// Array of second order dual numbers using 64-bit-floats.
+///
+/// Real, first and second derivative parts are stored as contiguous
+/// arrays and all operations are evaluated elementwise in Rust. Binary
+/// operations, also with numpy arrays, broadcast their operands like numpy.
+pub struct PyDual2Array64 {
+    pub _data: DualArray<Dual2_64>,
+}
+
+#[pymethods]
+impl PyDual2Array64 {
+    #[new]
+    pub fn new(re: &PyAny, v1: &PyAny, v2: &PyAny) -> PyResult<Self> {
+        let re = Variables::extract(re)?;
+        let v1 = Variables::extract(v1)?;
+        let v2 = Variables::extract(v2)?;
+        let shape = re.shape();
+        if v1.shape() != shape || v2.shape() != shape {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "real and derivative parts have to be of the same shape, got {:?}, {:?} and {:?}!",
+                shape,
+                v1.shape(),
+                v2.shape()
+            )));
+        }
+        let (re, v1, v2) = (re.into_values(), v1.into_values(), v2.into_values());
+        Ok(Self {
+            _data: DualArray {
+                shape,
+                parts: vec![re, v1, v2],
+                _phantom: PhantomData,
+            },
+        })
+    }
+
+    #[getter]
+    /// First derivative part.
+    pub fn get_first_derivative<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArrayDyn<f64>> {
+        self._data.part(py, 1)
+    }
+
+    #[getter]
+    /// Second derivative part.
+    pub fn get_second_derivative<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArrayDyn<f64>> {
+        self._data.part(py, 2)
+    }
+}
+
+impl_dual_array!(PyDual2Array64, "Dual2Array64", Dual2_64, PyDual2_64);
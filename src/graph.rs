@@ -0,0 +1,457 @@
+//! Expression graphs of operations on (hyper) dual numbers.
+//!
+//! A graph is a list of nodes in topological order. Every node refers to its
+//! operands by their index in the list. The graph can be evaluated for any
+//! type implementing `DualNum<f64>`, including `f64`, serialized to JSON and
+//! rendered as Graphviz DOT.
+use num_dual::{Dual64, DualNum};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Operations with a single operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOp {
+    Neg,
+    Abs,
+    Recip,
+    Sqrt,
+    Cbrt,
+    Exp,
+    Exp2,
+    ExpM1,
+    Ln,
+    Log2,
+    Log10,
+    Ln1p,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asinh,
+    Acosh,
+    Atanh,
+    SphJ0,
+    SphJ1,
+    SphJ2,
+}
+
+impl UnaryOp {
+    pub const ALL: [UnaryOp; 27] = [
+        UnaryOp::Neg,
+        UnaryOp::Abs,
+        UnaryOp::Recip,
+        UnaryOp::Sqrt,
+        UnaryOp::Cbrt,
+        UnaryOp::Exp,
+        UnaryOp::Exp2,
+        UnaryOp::ExpM1,
+        UnaryOp::Ln,
+        UnaryOp::Log2,
+        UnaryOp::Log10,
+        UnaryOp::Ln1p,
+        UnaryOp::Sin,
+        UnaryOp::Cos,
+        UnaryOp::Tan,
+        UnaryOp::Asin,
+        UnaryOp::Acos,
+        UnaryOp::Atan,
+        UnaryOp::Sinh,
+        UnaryOp::Cosh,
+        UnaryOp::Tanh,
+        UnaryOp::Asinh,
+        UnaryOp::Acosh,
+        UnaryOp::Atanh,
+        UnaryOp::SphJ0,
+        UnaryOp::SphJ1,
+        UnaryOp::SphJ2,
+    ];
+
+    /// Name of the operation (the name of the corresponding Python method).
+    pub fn name(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "negative",
+            UnaryOp::Abs => "abs",
+            UnaryOp::Recip => "recip",
+            UnaryOp::Sqrt => "sqrt",
+            UnaryOp::Cbrt => "cbrt",
+            UnaryOp::Exp => "exp",
+            UnaryOp::Exp2 => "exp2",
+            UnaryOp::ExpM1 => "expm1",
+            UnaryOp::Ln => "log",
+            UnaryOp::Log2 => "log2",
+            UnaryOp::Log10 => "log10",
+            UnaryOp::Ln1p => "log1p",
+            UnaryOp::Sin => "sin",
+            UnaryOp::Cos => "cos",
+            UnaryOp::Tan => "tan",
+            UnaryOp::Asin => "arcsin",
+            UnaryOp::Acos => "arccos",
+            UnaryOp::Atan => "arctan",
+            UnaryOp::Sinh => "sinh",
+            UnaryOp::Cosh => "cosh",
+            UnaryOp::Tanh => "tanh",
+            UnaryOp::Asinh => "arcsinh",
+            UnaryOp::Acosh => "arccosh",
+            UnaryOp::Atanh => "arctanh",
+            UnaryOp::SphJ0 => "sph_j0",
+            UnaryOp::SphJ1 => "sph_j1",
+            UnaryOp::SphJ2 => "sph_j2",
+        }
+    }
+
+    /// The operation with the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|op| op.name() == name)
+    }
+
+    pub fn eval<T: DualNum<f64> + Copy>(self, x: T) -> T {
+        match self {
+            UnaryOp::Neg => -x,
+            UnaryOp::Abs => {
+                if x.re() < 0.0 {
+                    -x
+                } else {
+                    x
+                }
+            }
+            UnaryOp::Recip => x.recip(),
+            UnaryOp::Sqrt => x.sqrt(),
+            UnaryOp::Cbrt => x.cbrt(),
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Exp2 => x.exp2(),
+            UnaryOp::ExpM1 => x.exp_m1(),
+            UnaryOp::Ln => x.ln(),
+            UnaryOp::Log2 => x.log2(),
+            UnaryOp::Log10 => x.log10(),
+            UnaryOp::Ln1p => x.ln_1p(),
+            UnaryOp::Sin => x.sin(),
+            UnaryOp::Cos => x.cos(),
+            UnaryOp::Tan => x.tan(),
+            UnaryOp::Asin => x.asin(),
+            UnaryOp::Acos => x.acos(),
+            UnaryOp::Atan => x.atan(),
+            UnaryOp::Sinh => x.sinh(),
+            UnaryOp::Cosh => x.cosh(),
+            UnaryOp::Tanh => x.tanh(),
+            UnaryOp::Asinh => x.asinh(),
+            UnaryOp::Acosh => x.acosh(),
+            UnaryOp::Atanh => x.atanh(),
+            UnaryOp::SphJ0 => x.sph_j0(),
+            UnaryOp::SphJ1 => x.sph_j1(),
+            UnaryOp::SphJ2 => x.sph_j2(),
+        }
+    }
+}
+
+/// Operations with two operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+impl BinaryOp {
+    pub const ALL: [BinaryOp; 5] = [
+        BinaryOp::Add,
+        BinaryOp::Sub,
+        BinaryOp::Mul,
+        BinaryOp::Div,
+        BinaryOp::Pow,
+    ];
+
+    /// Name of the operation.
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Pow => "pow",
+        }
+    }
+
+    /// Infix symbol of the operation.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Pow => "**",
+        }
+    }
+
+    /// The operation with the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|op| op.name() == name)
+    }
+
+    pub fn eval<T: DualNum<f64> + Copy>(self, x: T, y: T) -> T {
+        match self {
+            BinaryOp::Add => x + y,
+            BinaryOp::Sub => x - y,
+            BinaryOp::Mul => x * y,
+            BinaryOp::Div => x / y,
+            BinaryOp::Pow => (y * x.ln()).exp(),
+        }
+    }
+}
+
+/// Serialization of real numbers that writes NaN and infinities, which have
+/// no representation as JSON numbers, as the strings "nan", "inf" and "-inf".
+mod float {
+    use serde::de::{Error, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(x: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if x.is_finite() {
+            serializer.serialize_f64(*x)
+        } else if x.is_nan() {
+            serializer.serialize_str("nan")
+        } else if *x > 0.0 {
+            serializer.serialize_str("inf")
+        } else {
+            serializer.serialize_str("-inf")
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Float {
+        Number(f64),
+        String(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Float::deserialize(deserializer)? {
+            Float::Number(x) => Ok(x),
+            Float::String(s) => match s.as_str() {
+                "nan" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &"a number, \"nan\", \"inf\" or \"-inf\"",
+                )),
+            },
+        }
+    }
+}
+
+/// Node of an expression graph.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Node {
+    /// Independent variable with the given index.
+    Input(usize),
+    /// Real constant.
+    Const(#[serde(with = "float")] f64),
+    Unary(UnaryOp, usize),
+    Binary(BinaryOp, usize, usize),
+    /// Power with integer exponent.
+    Powi(usize, i32),
+    /// Power with real exponent.
+    Powf(usize, #[serde(with = "float")] f64),
+    /// Logarithm with real base.
+    Log(usize, #[serde(with = "float")] f64),
+    /// Fused multiply-add (a * b + c).
+    MulAdd(usize, usize, usize),
+}
+
+impl Node {
+    /// Indices of the operands of the node.
+    pub fn operands(&self) -> Vec<usize> {
+        match *self {
+            Node::Input(_) | Node::Const(_) => vec![],
+            Node::Unary(_, x) | Node::Powi(x, _) | Node::Powf(x, _) | Node::Log(x, _) => vec![x],
+            Node::Binary(_, x, y) => vec![x, y],
+            Node::MulAdd(a, b, c) => vec![a, b, c],
+        }
+    }
+
+    /// Short description of the node.
+    pub fn label(&self) -> String {
+        match *self {
+            Node::Input(i) => format!("x{}", i),
+            Node::Const(c) => format!("{}", c),
+            Node::Unary(op, _) => op.name().to_string(),
+            Node::Binary(op, _, _) => op.symbol().to_string(),
+            Node::Powi(_, n) => format!("powi({})", n),
+            Node::Powf(_, n) => format!("powf({})", n),
+            Node::Log(_, base) => format!("log_base({})", base),
+            Node::MulAdd(_, _, _) => "mul_add".to_string(),
+        }
+    }
+
+    /// Apply the operation of the node to the values of its operands.
+    fn apply<T: DualNum<f64> + Copy>(&self, x: &[T]) -> T {
+        match *self {
+            Node::Input(_) | Node::Const(_) => unreachable!(),
+            Node::Unary(op, _) => op.eval(x[0]),
+            Node::Binary(op, _, _) => op.eval(x[0], x[1]),
+            Node::Powi(_, n) => x[0].powi(n),
+            Node::Powf(_, n) => x[0].powf(n),
+            Node::Log(_, base) => x[0].log(base),
+            Node::MulAdd(_, _, _) => x[0].mul_add(x[1], x[2]),
+        }
+    }
+
+    fn eval<T: DualNum<f64> + From<f64> + Copy>(&self, inputs: &[T], values: &[T]) -> T {
+        match *self {
+            Node::Input(i) => inputs[i],
+            Node::Const(c) => T::from(c),
+            _ => {
+                let x: Vec<T> = self.operands().iter().map(|&o| values[o]).collect();
+                self.apply(&x)
+            }
+        }
+    }
+
+    /// Partial derivatives of the node with respect to each of its operands.
+    pub fn partials(&self, values: &[f64]) -> Vec<f64> {
+        let operands = self.operands();
+        (0..operands.len())
+            .map(|k| {
+                let x: Vec<Dual64> = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &o)| Dual64::new_scalar(values[o], if j == k { 1.0 } else { 0.0 }))
+                    .collect();
+                self.apply(&x).eps[0]
+            })
+            .collect()
+    }
+}
+
+/// Expression graph with a number of inputs and outputs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Graph {
+    pub inputs: usize,
+    pub nodes: Vec<Node>,
+    pub outputs: Vec<usize>,
+}
+
+impl Graph {
+    /// Graph that only contains the input nodes.
+    pub fn new(inputs: usize) -> Self {
+        Self {
+            inputs,
+            nodes: (0..inputs).map(Node::Input).collect(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Append a node and return its index.
+    pub fn push(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Values of all nodes.
+    pub fn eval_nodes<T: DualNum<f64> + From<f64> + Copy>(&self, inputs: &[T]) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = node.eval(inputs, &values);
+            values.push(value);
+        }
+        values
+    }
+
+    /// Values of the outputs.
+    pub fn eval<T: DualNum<f64> + From<f64> + Copy>(&self, inputs: &[T]) -> Vec<T> {
+        let values = self.eval_nodes(inputs);
+        self.outputs.iter().map(|&o| values[o]).collect()
+    }
+
+    /// Nodes on which at least one output depends.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        for &o in &self.outputs {
+            reachable[o] = true;
+        }
+        for i in (0..self.nodes.len()).rev() {
+            if reachable[i] {
+                for o in self.nodes[i].operands() {
+                    reachable[o] = true;
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Check that all indices refer to inputs or preceding nodes.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Node::Input(k) = *node {
+                if k >= self.inputs {
+                    return Err(format!("node {} refers to input {} of {}", i, k, self.inputs));
+                }
+            }
+            if let Some(o) = node.operands().into_iter().find(|&o| o >= i) {
+                return Err(format!("node {} refers to node {} that is not evaluated before", i, o));
+            }
+        }
+        if let Some(o) = self.outputs.iter().find(|&&o| o >= self.nodes.len()) {
+            return Err(format!("output refers to node {} of {}", o, self.nodes.len()));
+        }
+        Ok(())
+    }
+
+    /// Serialize the graph to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Read a graph from JSON.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let graph: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    /// Render the graph in the Graphviz DOT language.
+    ///
+    /// If the values of the inputs are given, every node is annotated with
+    /// its value and every edge with the partial derivative of the node
+    /// with respect to the operand.
+    pub fn to_dot(&self, inputs: Option<&[f64]>) -> String {
+        let values = inputs.map(|x| self.eval_nodes(x));
+        let mut dot = String::from("digraph expression {\n    rankdir=LR;\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let shape = match node {
+                Node::Input(_) => "ellipse",
+                Node::Const(_) => "plaintext",
+                _ => "box",
+            };
+            let label = match &values {
+                Some(v) => format!("{}\\n{}", node.label(), v[i]),
+                None => node.label(),
+            };
+            writeln!(dot, "    n{} [shape={}, label=\"{}\"];", i, shape, label).unwrap();
+            let partials = values.as_ref().map(|v| node.partials(v));
+            for (k, o) in node.operands().into_iter().enumerate() {
+                match &partials {
+                    Some(p) => writeln!(dot, "    n{} -> n{} [label=\"{}\"];", o, i, p[k]),
+                    None => writeln!(dot, "    n{} -> n{};", o, i),
+                }
+                .unwrap();
+            }
+        }
+        for (k, o) in self.outputs.iter().enumerate() {
+            writeln!(dot, "    y{} [shape=doublecircle, label=\"y{}\"];", k, k).unwrap();
+            writeln!(dot, "    n{} -> y{};", o, k).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -1,6 +1,8 @@
-use crate::dual::PyDual64;
+use crate::coerce::{extract_real, FromPyReal, Variables};
+use crate::dual::{seed_vec, PyDual64};
 use crate::dual2::{PyDual2Dual64, PyDual2_64};
 use num_dual::*;
+use numpy::{PyArray1, PyArray2};
 use pyo3::exceptions::PyTypeError;
 use pyo3::number::PyNumberProtocol;
 use pyo3::prelude::*;
@@ -41,10 +43,15 @@ pub struct PyHyperDual64 {
 #[pymethods]
 impl PyHyperDual64 {
     #[new]
-    pub fn new(re: f64, eps1: f64, eps2: f64, eps1eps2: f64) -> Self {
-        Self {
-            _data: HyperDual::new_scalar(re, eps1, eps2, eps1eps2),
-        }
+    pub fn new(re: &PyAny, eps1: &PyAny, eps2: &PyAny, eps1eps2: &PyAny) -> PyResult<Self> {
+        Ok(Self {
+            _data: HyperDual::new_scalar(
+                extract_real(re)?,
+                extract_real(eps1)?,
+                extract_real(eps2)?,
+                extract_real(eps1eps2)?,
+            ),
+        })
     }
 
     #[getter]
@@ -60,7 +67,10 @@ impl PyHyperDual64 {
     }
 }
 
-impl_dual_num!(PyHyperDual64, HyperDual64, f64);
+impl_dual_num!(PyHyperDual64, HyperDual64, f64, |x| {
+    // both directions coincide for the derivatives of a single variable
+    Some(x.eps1[0]).filter(|&e| e == x.eps2[0])
+});
 
 #[pyclass(name = "HyperDualDual64")]
 #[derive(Clone)]
@@ -72,10 +82,14 @@ pub struct PyHyperDualDual64 {
 #[pymethods]
 impl PyHyperDualDual64 {
     #[new]
-    pub fn new(re: PyDual64, eps1: PyDual64, eps2: PyDual64, eps1eps2: PyDual64) -> Self {
-        Self {
+    pub fn new(re: &PyAny, eps1: &PyAny, eps2: &PyAny, eps1eps2: &PyAny) -> PyResult<Self> {
+        let re = PyDual64::from_py_real(re)?;
+        let eps1 = PyDual64::from_py_real(eps1)?;
+        let eps2 = PyDual64::from_py_real(eps2)?;
+        let eps1eps2 = PyDual64::from_py_real(eps1eps2)?;
+        Ok(Self {
             _data: HyperDual::new_scalar(re._data, eps1._data, eps2._data, eps1eps2._data),
-        }
+        })
     }
 
     #[getter]
@@ -127,6 +141,21 @@ macro_rules! impl_hyper_dual_n {
                     .map(|a| a.to_vec())
                     .collect()
             }
+
+            #[getter]
+            /// Gradient as numpy array.
+            pub fn get_first_derivative_array<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+                PyArray1::from_slice(py, self._data.v1.raw_array())
+            }
+
+            #[getter]
+            /// Hessian as numpy array.
+            pub fn get_second_derivative_array<'py>(
+                &self,
+                py: Python<'py>,
+            ) -> PyResult<&'py PyArray2<f64>> {
+                PyArray1::from_vec(py, self._data.v2.raw_data().concat()).reshape([$n, $n])
+            }
         }
 
         impl_dual_num!($py_type_name, Dual2Vec64<$n>, f64);
@@ -172,72 +201,108 @@ macro_rules! impl_hyper_dual_mn {
             pub fn get_second_derivative(&self) -> Vec<Vec<f64>> {
                 self._data.eps1eps2.raw_data().iter().map(|a| a.to_vec()).collect()
             }
+
+            #[getter]
+            /// First hyperdual part as numpy arrays.
+            fn get_first_derivative_array<'py>(
+                &self,
+                py: Python<'py>,
+            ) -> (&'py PyArray1<f64>, &'py PyArray1<f64>) {
+                (
+                    PyArray1::from_slice(py, self._data.eps1.raw_array()),
+                    PyArray1::from_slice(py, self._data.eps2.raw_array()),
+                )
+            }
+
+            #[getter]
+            /// Hessian as numpy array.
+            pub fn get_second_derivative_array<'py>(
+                &self,
+                py: Python<'py>,
+            ) -> PyResult<&'py PyArray2<f64>> {
+                PyArray1::from_vec(py, self._data.eps1eps2.raw_data().concat()).reshape([$m, $n])
+            }
         }
 
         impl_dual_num!($py_type_name, HyperDualVec64<$m, $n>, f64);
     };
 }
 
+/// Seed `x1` and `x2` as two sets of independent variables of hyper dual numbers.
+fn seed_hyperdual_vec<P: IntoPy<PyObject>, const M: usize, const N: usize>(
+    py: Python,
+    x1: &[f64],
+    x2: &[f64],
+    new: impl Fn(f64, [f64; M], [f64; N], [[f64; N]; M]) -> P,
+) -> (Vec<PyObject>, Vec<PyObject>) {
+    let x1 = seed_vec(py, x1, &[true; M], |re, eps1| {
+        new(re, eps1, [0.0; N], [[0.0; N]; M])
+    });
+    let x2 = seed_vec(py, x2, &[true; N], |re, eps2| {
+        new(re, [0.0; M], eps2, [[0.0; N]; M])
+    });
+    (x1, x2)
+}
+
 macro_rules! impl_derive {
     ([$(($py_type_name:ident, $n:literal)),+; $(($py_type_name12:ident, $py_type_name21:ident, $m:literal)),+; $(($py_type_name3:ident, $m1:literal, $m2:literal)),+]) => {
         #[pyfunction]
-        #[pyo3(text_signature = "(x1, x2=None)")]
-        pub fn derive2(x1: &PyAny, x2: Option<&PyAny>) -> PyResult<PyObject> {
+        #[pyo3(text_signature = "(x1, x2=None, seed=None)")]
+        /// Seed the given value(s) as independent variables of (hyper) dual numbers.
+        ///
+        /// x1 and x2 can be scalars, sequences or arrays of any shape. The results
+        /// have the same structure. If only x1 is given, seed (a boolean array of
+        /// the same shape as x1) can be used to seed only the selected entries.
+        pub fn derive2(x1: &PyAny, x2: Option<&PyAny>, seed: Option<&PyAny>) -> PyResult<PyObject> {
             Python::with_gil(|py| {
                 match x2 {
                     None => {
-                        if let Ok(x) = x1.extract::<f64>() {
-                            return Ok(PyCell::new(py, PyDual2_64::from(Dual2_64::from(x).derive()))?.to_object(py));
-                        };
                         if let Ok(x) = x1.extract::<PyDual64>() {
                             return Ok(PyCell::new(py, PyDual2Dual64::from(Dual2::from_re(x._data).derive()))?.to_object(py));
                         };
-                        $(
-                            if let Ok(x) = x1.extract::<[f64; $n]>() {
-                                let arr = StaticVec::new_vec(x).map(Dual2Vec64::from).derive();
-                                let py_vec: Result<Vec<&PyCell<$py_type_name>>, _> = arr.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name::from(i))).collect();
-                                return Ok(py_vec?.to_object(py));
-                            };
-                        )+
+                        let x = Variables::extract(x1)?;
+                        let seed = x.seed(seed)?;
+                        let values = x.values();
+                        let res = match seed.iter().filter(|&&s| s).count() {
+                            1 => seed_vec(py, values, &seed, |re, [v1]: [f64; 1]| {
+                                PyDual2_64::from(Dual2_64::new_scalar(re, v1, 0.0))
+                            }),
+                            $($n => seed_vec(py, values, &seed, |re, v1| $py_type_name::new(re, v1, [[0.0; $n]; $n])),)+
+                            n => {
+                                return Err(PyErr::new::<PyTypeError, _>(format!(
+                                    "derive2 is not implemented for {} variables (at most 5)! Use seed to select the entries to differentiate.",
+                                    n
+                                )))
+                            }
+                        };
+                        x.wrap(py, res)
                     },
                     Some(x2) => {
-                        if let (Ok(x1), Ok(x2)) = (x1.extract::<f64>(), x2.extract::<f64>()) {
-                            let x1 = HyperDual64::from(x1).derive1();
-                            let x2 = HyperDual64::from(x2).derive2();
-                            let py_x1 = PyCell::new(py, PyHyperDual64::from(x1));
-                            let py_x2 = PyCell::new(py, PyHyperDual64::from(x2));
-                            return Ok((py_x1?, py_x2?).to_object(py));
+                        if seed.is_some() {
+                            return Err(PyErr::new::<PyTypeError, _>(
+                                "seed is only supported for a single set of variables!",
+                            ));
+                        }
+                        let (x1, x2) = (Variables::extract(x1)?, Variables::extract(x2)?);
+                        let (res1, res2) = match (x1.values().len(), x2.values().len()) {
+                            (1, 1) => {
+                                let v1 = HyperDual64::from(x1.values()[0]).derive1();
+                                let v2 = HyperDual64::from(x2.values()[0]).derive2();
+                                (vec![PyHyperDual64::from(v1).into_py(py)], vec![PyHyperDual64::from(v2).into_py(py)])
+                            },
+                            $((1, $m) => seed_hyperdual_vec(py, x1.values(), x2.values(), $py_type_name12::new),)+
+                            $(($m, 1) => seed_hyperdual_vec(py, x1.values(), x2.values(), $py_type_name21::new),)+
+                            $(($m1, $m2) => seed_hyperdual_vec(py, x1.values(), x2.values(), $py_type_name3::new),)+
+                            (n1, n2) => {
+                                return Err(PyErr::new::<PyTypeError, _>(format!(
+                                    "derive2 is not implemented for {} and {} variables!",
+                                    n1, n2
+                                )))
+                            }
                         };
-                        $(
-                            if let (Ok(x1), Ok(x2)) = (x1.extract::<f64>(), x2.extract::<[f64; $m]>()) {
-                                let x1 = HyperDualVec64::from(x1).derive1();
-                                let arr2 = StaticVec::new_vec(x2).map(HyperDualVec64::from).derive2();
-                                let py_x1 = PyCell::new(py, $py_type_name12::from(x1));
-                                let py_vec2: Result<Vec<&PyCell<$py_type_name12>>, _> = arr2.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name12::from(i))).collect();
-                                return Ok((py_x1?, py_vec2?).to_object(py));
-                            };
-                        )+
-                        $(
-                            if let (Ok(x1), Ok(x2)) = (x1.extract::<[f64; $m]>(), x2.extract::<f64>(), ) {
-                                let arr1 = StaticVec::new_vec(x1).map(HyperDualVec64::from).derive1();
-                                let x2 = HyperDualVec64::from(x2).derive2();
-                                let py_vec1: Result<Vec<&PyCell<$py_type_name21>>, _> = arr1.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name21::from(i))).collect();
-                                let py_x2 = PyCell::new(py, $py_type_name21::from(x2));
-                                return Ok((py_vec1?, py_x2?).to_object(py));
-                            };
-                        )+
-                        $(
-                            if let (Ok(x1), Ok(x2)) = (x1.extract::<[f64; $m1]>(), x2.extract::<[f64; $m2]>()) {
-                                let arr1 = StaticVec::new_vec(x1).map(HyperDualVec64::from).derive1();
-                                let arr2 = StaticVec::new_vec(x2).map(HyperDualVec64::from).derive2();
-                                let py_vec1: Result<Vec<&PyCell<$py_type_name3>>, _> = arr1.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name3::from(i))).collect();
-                                let py_vec2: Result<Vec<&PyCell<$py_type_name3>>, _> = arr2.raw_array().iter().map(|&i| PyCell::new(py, $py_type_name3::from(i))).collect();
-                                return Ok((py_vec1?, py_vec2?).to_object(py));
-                            };
-                        )+
+                        Ok((x1.wrap(py, res1)?, x2.wrap(py, res2)?).to_object(py))
                     }
-                };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                }
             })
         }
         $(impl_hyper_dual_n!($py_type_name, $n);)+
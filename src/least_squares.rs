@@ -0,0 +1,327 @@
+//! Nonlinear least-squares fitting with the Levenberg-Marquardt method and
+//! Jacobians from vector dual numbers.
+use crate::coerce::Variables;
+use crate::derivatives::{jacobian, vector_at};
+use crate::linalg::{dot, max_norm, norm, Cholesky};
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Initial damping parameter relative to the largest diagonal entry of J^T J.
+const INITIAL_DAMPING: f64 = 1e-3;
+
+fn cost(r: &[f64]) -> f64 {
+    0.5 * dot(r, r)
+}
+
+/// J^T J (flat) and J^T r.
+fn normal_equations(jac: &[Vec<f64>], r: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut jtj = vec![0.0; n * n];
+    let mut jtr = vec![0.0; n];
+    for (row, r) in jac.iter().zip(r) {
+        for (i, &ji) in row.iter().enumerate() {
+            jtr[i] += ji * r;
+            for (j, &jj) in row.iter().enumerate() {
+                jtj[i * n + j] += ji * jj;
+            }
+        }
+    }
+    (jtj, jtr)
+}
+
+#[pyclass(name = "LeastSquaresResult")]
+/// Result of `least_squares`.
+pub struct PyLeastSquaresResult {
+    shape: Vec<usize>,
+    x: Vec<f64>,
+    fun: Vec<f64>,
+    jac: Vec<Vec<f64>>,
+    converged: bool,
+    flag: String,
+    iterations: usize,
+    function_calls: usize,
+    history: Vec<(f64, f64, f64, f64)>,
+}
+
+impl PyLeastSquaresResult {
+    /// Estimated covariance s^2 (J^T J)^-1 with s^2 = sum(r^2) / (m - n).
+    ///
+    /// All entries are infinite if the Jacobian does not have full rank or
+    /// there are not more residuals than parameters.
+    fn covariance(&self) -> Vec<f64> {
+        let (m, n) = (self.fun.len(), self.x.len());
+        let (jtj, _) = normal_equations(&self.jac, &self.fun, n);
+        match Cholesky::new(&jtj, n) {
+            Some(chol) if m > n => {
+                let s2 = dot(&self.fun, &self.fun) / (m - n) as f64;
+                let mut cov = vec![0.0; n * n];
+                for j in 0..n {
+                    let mut e = vec![0.0; n];
+                    e[j] = 1.0;
+                    for (i, c) in chol.solve(&e).into_iter().enumerate() {
+                        cov[i * n + j] = s2 * c;
+                    }
+                }
+                cov
+            }
+            _ => vec![f64::INFINITY; n * n],
+        }
+    }
+}
+
+#[pymethods]
+impl PyLeastSquaresResult {
+    #[getter]
+    /// Fitted parameters in the same structure as the initial guess.
+    pub fn get_x(&self, py: Python) -> PyResult<PyObject> {
+        if self.shape.is_empty() {
+            return Ok(self.x[0].into_py(py));
+        }
+        Ok(PyArray1::from_slice(py, &self.x)
+            .reshape(self.shape.as_slice())?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Residuals at the solution.
+    pub fn get_fun<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.fun)
+    }
+
+    #[getter]
+    /// Jacobian of the residuals at the solution.
+    pub fn get_jac(&self, py: Python) -> PyResult<PyObject> {
+        let n = self.x.len();
+        Ok(PyArray1::from_vec(py, self.jac.concat())
+            .reshape([self.jac.len(), n])?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Value of the cost function 0.5 * sum(r^2) at the solution.
+    pub fn get_cost(&self) -> f64 {
+        cost(&self.fun)
+    }
+
+    #[getter]
+    /// Estimated covariance matrix of the parameters s^2 (J^T J)^-1 with
+    /// s^2 = sum(r^2) / (m - n).
+    pub fn get_cov(&self, py: Python) -> PyResult<PyObject> {
+        let n = self.x.len();
+        Ok(PyArray1::from_vec(py, self.covariance())
+            .reshape([n, n])?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Whether one of the convergence criteria is satisfied.
+    pub fn get_converged(&self) -> bool {
+        self.converged
+    }
+
+    #[getter]
+    /// Description of the cause of termination.
+    pub fn get_flag(&self) -> &str {
+        &self.flag
+    }
+
+    #[getter]
+    /// Number of iterations.
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    #[getter]
+    /// Number of evaluations of the residuals (with real or dual numbers).
+    pub fn get_function_calls(&self) -> usize {
+        self.function_calls
+    }
+
+    #[getter]
+    /// Iterations as list of (cost, gradient norm, step norm, damping).
+    pub fn get_history(&self) -> Vec<(f64, f64, f64, f64)> {
+        self.history.clone()
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyLeastSquaresResult {
+    fn __repr__(&self) -> PyResult<String> {
+        let mut lines = vec![
+            format!(
+                "LeastSquaresResult(cost={}, converged={}, flag='{}', iterations={}, function_calls={})",
+                self.get_cost(),
+                if self.converged { "True" } else { "False" },
+                self.flag,
+                self.iterations,
+                self.function_calls
+            ),
+            format!(
+                "{:<6}{:>24}{:>16}{:>16}{:>12}",
+                "iter", "cost", "|grad|", "|step|", "damping"
+            ),
+        ];
+        for (k, (cost, g, step, damping)) in self.history.iter().enumerate() {
+            lines.push(format!(
+                "{:<6}{:>24.16e}{:>16.8e}{:>16.8e}{:>12.2e}",
+                k + 1,
+                cost,
+                g,
+                step,
+                damping
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[pyfunction(ftol = "1e-10", xtol = "1e-10", gtol = "1e-10", maxiter = "100")]
+#[pyo3(text_signature = "(residuals, p0, ftol=1e-10, xtol=1e-10, gtol=1e-10, maxiter=100)")]
+/// Minimize the sum of squared residuals with the Levenberg-Marquardt method.
+///
+/// The Jacobian of the residuals is calculated exactly by evaluating
+/// `residuals` with vector dual numbers (one evaluation for up to 10
+/// parameters, one evaluation per parameter otherwise).
+///
+/// Parameters
+/// ----------
+/// residuals : callable
+///     Function called with the same structure as `p0` that returns the
+///     residuals (as sequence or array).
+/// p0 : float | list[float] | numpy.ndarray
+///     Initial guess of the parameters.
+/// ftol : float, optional
+///     Tolerance of the relative reduction of the cost, defaults to 1e-10.
+/// xtol : float, optional
+///     Tolerance of the step size relative to the parameters, defaults to 1e-10.
+/// gtol : float, optional
+///     Tolerance of the maximum norm of the gradient J^T r, defaults to 1e-10.
+/// maxiter : int, optional
+///     Maximum number of iterations, defaults to 100.
+///
+/// Returns
+/// -------
+/// LeastSquaresResult
+///     The fitted parameters `x`, the residuals `fun`, the Jacobian `jac`,
+///     the estimated covariance `cov` and convergence diagnostics.
+///
+/// Examples
+///
+/// >>> from dualnum import least_squares
+/// >>> import numpy as np
+/// >>> t = np.linspace(0.0, 1.0, 10)
+/// >>> y = 2.0 * np.exp(-1.5 * t)
+/// >>> res = least_squares(lambda p: [p[0] * np.exp(-p[1] * ti) - yi for ti, yi in zip(t, y)], [1.0, 1.0])
+/// >>> np.allclose(res.x, [2.0, 1.5])
+/// True
+pub fn least_squares(
+    py: Python,
+    residuals: &PyAny,
+    p0: &PyAny,
+    ftol: f64,
+    xtol: f64,
+    gtol: f64,
+    maxiter: usize,
+) -> PyResult<PyLeastSquaresResult> {
+    let variables = Variables::extract(p0)?;
+    let mut x = variables.values().to_vec();
+    let n = x.len();
+    if n == 0 {
+        return Err(PyErr::new::<PyValueError, _>(
+            "p0 has to contain at least one value!",
+        ));
+    }
+    // Number of evaluations of `residuals` required for the Jacobian.
+    let jacobian_calls = if n <= 10 { 1 } else { n };
+
+    let (fun, jac) = jacobian(py, residuals, &variables, &x)?;
+    if fun.is_empty() {
+        return Err(PyErr::new::<PyValueError, _>(
+            "residuals has to return at least one value!",
+        ));
+    }
+    let mut result = PyLeastSquaresResult {
+        shape: variables.shape(),
+        x: Vec::new(),
+        fun,
+        jac,
+        converged: false,
+        flag: String::from("maximum number of iterations exceeded"),
+        iterations: 0,
+        function_calls: jacobian_calls,
+        history: Vec::new(),
+    };
+
+    let (jtj, _) = normal_equations(&result.jac, &result.fun, n);
+    let max_diag = (0..n).map(|i| jtj[i * n + i]).fold(0.0, f64::max);
+    let mut damping = INITIAL_DAMPING * max_diag.max(f64::EPSILON);
+    let mut nu = 2.0;
+    loop {
+        let (mut jtj, jtr) = normal_equations(&result.jac, &result.fun, n);
+        let g_norm = max_norm(&jtr);
+        if g_norm <= gtol {
+            result.converged = true;
+            result.flag = String::from("the gradient is below gtol");
+            break;
+        }
+        if result.iterations == maxiter {
+            break;
+        }
+        let c = cost(&result.fun);
+        if !c.is_finite() {
+            result.flag = String::from("the residuals are not finite");
+            break;
+        }
+        result.iterations += 1;
+
+        for i in 0..n {
+            jtj[i * n + i] += damping;
+        }
+        let neg_g: Vec<f64> = jtr.iter().map(|g| -g).collect();
+        let h = match Cholesky::new(&jtj, n) {
+            Some(chol) => chol.solve(&neg_g),
+            None => {
+                damping *= nu;
+                nu *= 2.0;
+                continue;
+            }
+        };
+        let h_norm = norm(&h);
+        if h_norm <= xtol * (norm(&x) + xtol) {
+            result.converged = true;
+            result.flag = String::from("the step size is below xtol");
+            break;
+        }
+
+        let x_trial: Vec<f64> = x.iter().zip(&h).map(|(x, h)| x + h).collect();
+        let r_trial = vector_at(py, residuals, &variables, &x_trial)?;
+        result.function_calls += 1;
+        let c_trial = cost(&r_trial);
+        let predicted = 0.5 * (damping * dot(&h, &h) - dot(&h, &jtr));
+        let rho = if c_trial.is_finite() {
+            (c - c_trial) / predicted
+        } else {
+            f64::NEG_INFINITY
+        };
+        result.history.push((c, g_norm, h_norm, damping));
+        if rho > 0.0 {
+            x = x_trial;
+            let (fun, jac) = jacobian(py, residuals, &variables, &x)?;
+            result.function_calls += jacobian_calls;
+            result.fun = fun;
+            result.jac = jac;
+            damping *= (1.0 - (2.0 * rho - 1.0).powi(3)).max(1.0 / 3.0);
+            nu = 2.0;
+            if c - c_trial <= ftol * c {
+                result.converged = true;
+                result.flag = String::from("the reduction of the cost is below ftol");
+                break;
+            }
+        } else {
+            damping *= nu;
+            nu *= 2.0;
+        }
+    }
+    result.x = x;
+    Ok(result)
+}
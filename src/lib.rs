@@ -1,24 +1,111 @@
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
+#[cfg(feature = "python")]
 use pyo3::wrap_pyfunction;
 
+#[cfg(feature = "python")]
 #[macro_use]
 mod macros;
+#[cfg(feature = "python")]
+mod array_function;
+#[cfg(feature = "python")]
+mod check;
+pub mod codegen;
+#[cfg(feature = "python")]
+mod coerce;
+#[cfg(feature = "python")]
+mod custom;
+#[cfg(feature = "python")]
+mod derivatives;
+#[cfg(feature = "python")]
+mod dtype;
+#[cfg(feature = "python")]
 mod dual;
+#[cfg(feature = "python")]
 mod dual2;
+#[cfg(feature = "python")]
 mod dual3;
+#[cfg(feature = "python")]
+mod dual_array;
+pub mod graph;
+#[cfg(feature = "python")]
 mod hyperdual;
+#[cfg(feature = "python")]
+mod least_squares;
+#[cfg(feature = "python")]
+mod linalg;
+#[cfg(feature = "python")]
+mod minimize;
+pub mod parser;
+#[cfg(feature = "python")]
+mod quad;
+#[cfg(feature = "python")]
+mod root;
+#[cfg(feature = "python")]
+mod scipy;
+#[cfg(feature = "python")]
+mod solve;
+#[cfg(feature = "python")]
+mod testing;
+#[cfg(feature = "python")]
+mod trace;
 
+#[cfg(feature = "python")]
+use check::__pyo3_get_function_check_derivatives;
+#[cfg(feature = "python")]
+use custom::__pyo3_get_function_custom_function;
+#[cfg(feature = "python")]
 use dual::__pyo3_get_function_derive1;
+#[cfg(feature = "python")]
 use dual3::__pyo3_get_function_derive3;
+#[cfg(feature = "python")]
 use hyperdual::__pyo3_get_function_derive2;
+#[cfg(feature = "python")]
+use least_squares::__pyo3_get_function_least_squares;
+#[cfg(feature = "python")]
+use linalg::__pyo3_get_function_eigh;
+#[cfg(feature = "python")]
+use minimize::__pyo3_get_function_minimize;
+#[cfg(feature = "python")]
+use quad::__pyo3_get_function_quad;
+#[cfg(feature = "python")]
+use root::{
+    __pyo3_get_function_cubic_roots, __pyo3_get_function_implicit_root,
+    __pyo3_get_function_root_scalar,
+};
+#[cfg(feature = "python")]
+use solve::__pyo3_get_function_solve_newton;
+#[cfg(feature = "python")]
+use trace::{__pyo3_get_function_evaluate, __pyo3_get_function_trace};
 
+#[cfg(feature = "python")]
+pub use check::{PyDerivativeCheck, PyDerivativeComparison};
+#[cfg(feature = "python")]
+pub use custom::PyCustomFunction;
+#[cfg(feature = "python")]
 pub use dual::PyDual64;
+#[cfg(feature = "python")]
 pub use dual2::{PyDual2Dual64, PyDual2_64};
+#[cfg(feature = "python")]
 pub use dual3::{PyDual3Dual64, PyDual3_64};
+#[cfg(feature = "python")]
+pub use dual_array::{PyDual2Array64, PyDualArray64};
+#[cfg(feature = "python")]
 pub use hyperdual::{PyHyperDual64, PyHyperDualDual64};
+#[cfg(feature = "python")]
+pub use least_squares::PyLeastSquaresResult;
+#[cfg(feature = "python")]
+pub use minimize::PyMinimizeResult;
+#[cfg(feature = "python")]
+pub use root::PyRootResult;
+#[cfg(feature = "python")]
+pub use solve::PySolveResult;
+#[cfg(feature = "python")]
+pub use trace::{PyExpression, PyTracer};
 
+#[cfg(feature = "python")]
 #[pymodule]
-fn dualnum(_py: Python, m: &PyModule) -> PyResult<()> {
+fn dualnum(py: Python, m: &PyModule) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     m.add_class::<PyDual64>()?;
     m.add_class::<PyHyperDual64>()?;
@@ -27,8 +114,36 @@ fn dualnum(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyHyperDualDual64>()?;
     m.add_class::<PyDual2Dual64>()?;
     m.add_class::<PyDual3Dual64>()?;
+    m.add_class::<PyDualArray64>()?;
+    m.add_class::<PyDual2Array64>()?;
+    m.add_class::<PyTracer>()?;
+    m.add_class::<PyExpression>()?;
+    m.add_class::<PyCustomFunction>()?;
+    m.add_class::<PyDerivativeCheck>()?;
+    m.add_class::<PyDerivativeComparison>()?;
+    m.add_class::<PyRootResult>()?;
+    m.add_class::<PySolveResult>()?;
+    m.add_class::<PyMinimizeResult>()?;
+    m.add_class::<PyLeastSquaresResult>()?;
     m.add_function(wrap_pyfunction!(derive1, m)?).unwrap();
     m.add_function(wrap_pyfunction!(derive2, m)?).unwrap();
     m.add_function(wrap_pyfunction!(derive3, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(trace, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(evaluate, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(custom_function, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(check_derivatives, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(root_scalar, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(implicit_root, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(cubic_roots, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(solve_newton, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(minimize, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(least_squares, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(eigh, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(quad, m)?).unwrap();
+    m.add_submodule(testing::testing(py)?)?;
+    m.add_submodule(scipy::scipy(py)?)?;
+    m.add_submodule(linalg::linalg(py)?)?;
+    array_function::register_object_array(py, m)?;
+    dtype::register_dtypes(py, m)?;
     Ok(())
 }
@@ -0,0 +1,624 @@
+//! Dense linear algebra for real and dual numbers.
+//!
+//! Matrices are stored row by row in flat vectors. Pivots are chosen by the
+//! real parts, so that the derivatives are propagated through the same
+//! sequence of operations as the real parts.
+use crate::coerce::{extract_array, PyDual};
+use num_dual::DualNum;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::wrap_pyfunction;
+
+/// Maximum norm of a vector.
+pub fn max_norm(x: &[f64]) -> f64 {
+    x.iter().fold(0.0, |acc, x| acc.max(x.abs()))
+}
+
+/// Euclidean norm of a vector.
+pub fn norm(x: &[f64]) -> f64 {
+    dot(x, x).sqrt()
+}
+
+/// Scalar product of two vectors.
+pub fn dot(x: &[f64], y: &[f64]) -> f64 {
+    x.iter().zip(y).map(|(x, y)| x * y).sum()
+}
+
+/// LU decomposition with partial pivoting, P A = L U.
+pub struct LU<T> {
+    n: usize,
+    lu: Vec<T>,
+    pivots: Vec<usize>,
+    sign: f64,
+}
+
+impl<T: DualNum<f64> + From<f64> + Copy> LU<T> {
+    /// Decompose the n x n matrix `a`.
+    ///
+    /// Returns `None` if a pivot is zero, i.e., the matrix is singular.
+    pub fn new(mut a: Vec<T>, n: usize) -> Option<Self> {
+        let mut pivots: Vec<usize> = (0..n).collect();
+        let mut sign = 1.0;
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&i, &j| {
+                    let (ai, aj) = (a[i * n + k].re().abs(), a[j * n + k].re().abs());
+                    ai.partial_cmp(&aj).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap();
+            let pivot = a[p * n + k].re();
+            if pivot == 0.0 || !pivot.is_finite() {
+                return None;
+            }
+            if p != k {
+                for j in 0..n {
+                    a.swap(k * n + j, p * n + j);
+                }
+                pivots.swap(k, p);
+                sign = -sign;
+            }
+            for i in k + 1..n {
+                let factor = a[i * n + k] / a[k * n + k];
+                a[i * n + k] = factor;
+                for j in k + 1..n {
+                    a[i * n + j] = a[i * n + j] - factor * a[k * n + j];
+                }
+            }
+        }
+        Some(Self {
+            n,
+            lu: a,
+            pivots,
+            sign,
+        })
+    }
+
+    /// Solve A x = b.
+    pub fn solve(&self, b: &[T]) -> Vec<T> {
+        let n = self.n;
+        let mut x: Vec<T> = self.pivots.iter().map(|&p| b[p]).collect();
+        for i in 0..n {
+            for j in 0..i {
+                x[i] = x[i] - self.lu[i * n + j] * x[j];
+            }
+        }
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] = x[i] - self.lu[i * n + j] * x[j];
+            }
+            x[i] = x[i] / self.lu[i * n + i];
+        }
+        x
+    }
+
+    /// Inverse of A.
+    pub fn inverse(&self) -> Vec<T> {
+        let n = self.n;
+        let mut inv = vec![T::from(0.0); n * n];
+        for j in 0..n {
+            let mut e = vec![T::from(0.0); n];
+            e[j] = T::from(1.0);
+            for (i, x) in self.solve(&e).into_iter().enumerate() {
+                inv[i * n + j] = x;
+            }
+        }
+        inv
+    }
+
+    /// Determinant of A.
+    pub fn det(&self) -> T {
+        (0..self.n).fold(T::from(self.sign), |det, i| det * self.lu[i * self.n + i])
+    }
+
+    /// Permutation matrix P (as flat vector), and the factors L and U
+    /// with A = P L U.
+    pub fn factors(&self) -> (Vec<f64>, Vec<T>, Vec<T>) {
+        let n = self.n;
+        let mut p = vec![0.0; n * n];
+        let mut l = vec![T::from(0.0); n * n];
+        let mut u = vec![T::from(0.0); n * n];
+        for i in 0..n {
+            p[self.pivots[i] * n + i] = 1.0;
+            l[i * n + i] = T::from(1.0);
+            for j in 0..n {
+                if j < i {
+                    l[i * n + j] = self.lu[i * n + j];
+                } else {
+                    u[i * n + j] = self.lu[i * n + j];
+                }
+            }
+        }
+        (p, l, u)
+    }
+}
+
+/// Determinant of the n x n matrix `a` with Bird's division-free algorithm
+/// in O(n^4) operations.
+///
+/// Used if the real part of the matrix is singular: the pivots of the LU
+/// decomposition then cannot be inverted, but the derivatives of the
+/// determinant do not vanish in general.
+fn det_division_free<T: DualNum<f64> + From<f64> + Copy>(a: &[T], n: usize) -> T {
+    if n == 0 {
+        return T::from(1.0);
+    }
+    let mut x = a.to_vec();
+    for _ in 1..n {
+        // strict upper triangle of x with the negative sums of the
+        // following diagonal entries on the diagonal
+        let mut mu = vec![T::from(0.0); n * n];
+        let mut trace = T::from(0.0);
+        for i in (0..n).rev() {
+            mu[i * n + i] = -trace;
+            trace = trace + x[i * n + i];
+            for j in i + 1..n {
+                mu[i * n + j] = x[i * n + j];
+            }
+        }
+        x = (0..n * n)
+            .map(|k| {
+                let (i, j) = (k / n, k % n);
+                (i..n).fold(T::from(0.0), |acc, l| acc + mu[i * n + l] * a[l * n + j])
+            })
+            .collect();
+    }
+    if n % 2 == 0 {
+        -x[0]
+    } else {
+        x[0]
+    }
+}
+
+/// Cholesky decomposition A = L L^T of a symmetric positive definite matrix.
+pub struct Cholesky<T> {
+    n: usize,
+    l: Vec<T>,
+}
+
+impl<T: DualNum<f64> + From<f64> + Copy> Cholesky<T> {
+    /// Decompose the n x n matrix `a`, only the lower triangle is used.
+    ///
+    /// Returns `None` if the matrix is not positive definite.
+    pub fn new(a: &[T], n: usize) -> Option<Self> {
+        let mut l = vec![T::from(0.0); n * n];
+        for j in 0..n {
+            let mut d = a[j * n + j];
+            for k in 0..j {
+                d = d - l[j * n + k] * l[j * n + k];
+            }
+            if d.re() <= 0.0 || !d.re().is_finite() {
+                return None;
+            }
+            let d = d.sqrt();
+            l[j * n + j] = d;
+            for i in j + 1..n {
+                let mut s = a[i * n + j];
+                for k in 0..j {
+                    s = s - l[i * n + k] * l[j * n + k];
+                }
+                l[i * n + j] = s / d;
+            }
+        }
+        Some(Self { n, l })
+    }
+
+    /// Solve L y = b.
+    pub fn solve_lower(&self, b: &[T]) -> Vec<T> {
+        let n = self.n;
+        let mut y = b.to_vec();
+        for i in 0..n {
+            for j in 0..i {
+                y[i] = y[i] - self.l[i * n + j] * y[j];
+            }
+            y[i] = y[i] / self.l[i * n + i];
+        }
+        y
+    }
+
+    /// Solve A x = b.
+    pub fn solve(&self, b: &[T]) -> Vec<T> {
+        let n = self.n;
+        let mut x = self.solve_lower(b);
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] = x[i] - self.l[j * n + i] * x[j];
+            }
+            x[i] = x[i] / self.l[i * n + i];
+        }
+        x
+    }
+
+    /// Lower triangular factor L (as flat vector).
+    pub fn into_lower(self) -> Vec<T> {
+        self.l
+    }
+}
+
+/// Maximum number of sweeps of the Jacobi method for the real parts.
+const MAX_SWEEPS: usize = 100;
+/// Number of Jacobi sweeps with dual numbers. The off-diagonal entries are of
+/// order 2^k in the derivatives after k sweeps, which makes derivatives up to
+/// order 15 exact.
+const DUAL_SWEEPS: usize = 4;
+/// Relative distance below which two eigenvalues are treated as degenerate.
+const DEGENERACY: f64 = 1e-10;
+
+/// Apply the Jacobi rotation with angle arctan(t) in the (p, q) plane to the
+/// symmetric matrix `a` (a <- J^T a J) and to the eigenvectors `v` (v <- v J).
+fn rotate<T: DualNum<f64> + From<f64> + Copy>(
+    a: &mut [T],
+    v: &mut [T],
+    n: usize,
+    p: usize,
+    q: usize,
+    t: T,
+) {
+    let c = (t * t + T::from(1.0)).sqrt().recip();
+    let s = t * c;
+    for r in 0..n {
+        let (arp, arq) = (a[r * n + p], a[r * n + q]);
+        a[r * n + p] = c * arp - s * arq;
+        a[r * n + q] = s * arp + c * arq;
+        let (vrp, vrq) = (v[r * n + p], v[r * n + q]);
+        v[r * n + p] = c * vrp - s * vrq;
+        v[r * n + q] = s * vrp + c * vrq;
+    }
+    for r in 0..n {
+        let (apr, aqr) = (a[p * n + r], a[q * n + r]);
+        a[p * n + r] = c * apr - s * aqr;
+        a[q * n + r] = s * apr + c * aqr;
+    }
+    a[p * n + q] = T::from(0.0);
+    a[q * n + p] = T::from(0.0);
+}
+
+/// Eigenvalues (in ascending order) and eigenvectors (as columns) of a real
+/// symmetric matrix with the cyclic Jacobi method.
+fn jacobi(mut a: Vec<f64>, n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    let scale = norm(&a);
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..n)
+            .flat_map(|p| (p + 1..n).map(move |q| (p, q)))
+            .map(|(p, q)| a[p * n + q] * a[p * n + q])
+            .sum();
+        if off.sqrt() <= f64::EPSILON * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                rotate(&mut a, &mut v, n, p, q, t);
+            }
+        }
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| {
+        a[i * n + i]
+            .partial_cmp(&a[j * n + j])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let w = order.iter().map(|&i| a[i * n + i]).collect();
+    let v = (0..n * n).map(|k| v[k / n * n + order[k % n]]).collect();
+    (w, v)
+}
+
+/// Transform the block `start..start + m` of the rows and columns of the
+/// symmetric matrix `a` with the orthogonal matrix `u` (a <- U^T a U) and
+/// the corresponding columns of the eigenvectors `v` (v <- v U).
+fn rotate_block<T: DualNum<f64> + From<f64> + Copy>(
+    a: &mut [T],
+    v: &mut [T],
+    n: usize,
+    start: usize,
+    u: &[f64],
+    m: usize,
+) {
+    let combine = |x: &[T], stride: usize, offset: usize, j: usize| {
+        (0..m).fold(T::from(0.0), |acc, k| {
+            acc + x[offset + (start + k) * stride] * u[k * m + j]
+        })
+    };
+    for r in 0..n {
+        let ar: Vec<_> = (0..m).map(|j| combine(a, 1, r * n, j)).collect();
+        let vr: Vec<_> = (0..m).map(|j| combine(v, 1, r * n, j)).collect();
+        for j in 0..m {
+            a[r * n + start + j] = ar[j];
+            v[r * n + start + j] = vr[j];
+        }
+    }
+    for c in 0..n {
+        let ac: Vec<_> = (0..m).map(|i| combine(a, n, c, i)).collect();
+        for i in 0..m {
+            a[(start + i) * n + c] = ac[i];
+        }
+    }
+}
+
+/// Eigenvalues and eigenvectors of a symmetric matrix of dual numbers.
+///
+/// The real parts are diagonalized first. The transformed matrix V^T A V is
+/// diagonal in the real parts, and further Jacobi sweeps with dual numbers
+/// remove the off-diagonal derivatives, which yields the derivatives of
+/// first order perturbation theory and its higher order analogues.
+///
+/// Rotations between degenerate eigenvalues are skipped. Instead, the
+/// eigenvectors within a degenerate subspace are chosen to diagonalize the
+/// first derivative of V^T A V in that subspace (degenerate perturbation
+/// theory), if `first_derivative` provides it.
+fn eigh_dual<T: DualNum<f64> + From<f64> + Copy>(
+    a: &[T],
+    n: usize,
+    first_derivative: impl Fn(&T) -> Option<f64>,
+) -> (Vec<T>, Vec<T>) {
+    let (w0, v0) = jacobi(a.iter().map(|a| a.re()).collect(), n);
+    let scale = w0.iter().fold(0.0, |acc: f64, w| acc.max(w.abs()));
+    let mut v: Vec<T> = v0.iter().map(|&v| T::from(v)).collect();
+
+    // b = V^T A V
+    let mut av = vec![T::from(0.0); n * n];
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                av[i * n + j] = av[i * n + j] + a[i * n + k] * v[k * n + j];
+            }
+        }
+    }
+    let mut b = vec![T::from(0.0); n * n];
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                b[i * n + j] = b[i * n + j] + v[k * n + i] * av[k * n + j];
+            }
+        }
+    }
+
+    let mut start = 0;
+    while start < n {
+        let m = (start..n)
+            .take_while(|&q| (w0[q] - w0[start]).abs() <= DEGENERACY * scale)
+            .count();
+        if m > 1 {
+            let d: Option<Vec<f64>> = (0..m * m)
+                .map(|k| first_derivative(&b[(start + k / m) * n + start + k % m]))
+                .collect();
+            if let Some(d) = d {
+                let (_, u) = jacobi(d, m);
+                rotate_block(&mut b, &mut v, n, start, &u, m);
+            }
+        }
+        start += m;
+    }
+
+    for _ in 0..DUAL_SWEEPS {
+        for p in 0..n {
+            for q in p + 1..n {
+                if (w0[q] - w0[p]).abs() <= DEGENERACY * scale {
+                    continue;
+                }
+                let tau = b[p * n + q] * 2.0 / (b[q * n + q] - b[p * n + p]);
+                let t = tau / ((tau * tau + T::from(1.0)).sqrt() + T::from(1.0));
+                rotate(&mut b, &mut v, n, p, q, t);
+            }
+        }
+    }
+    ((0..n).map(|i| b[i * n + i]).collect(), v)
+}
+
+fn singular() -> PyErr {
+    PyErr::new::<PyValueError, _>("the matrix is singular!")
+}
+
+/// Entries of a square matrix of (dual) numbers as flat vector and its dimension.
+fn extract_square_matrix<P: PyDual>(a: &PyAny) -> PyResult<(usize, Vec<P::Data>)> {
+    let (shape, data) = extract_array::<P>(a)?;
+    match shape.as_slice() {
+        &[n, m] if n == m => Ok((n, data)),
+        _ => Err(PyErr::new::<PyValueError, _>(format!(
+            "expected a square matrix, got an array of shape {:?}",
+            shape
+        ))),
+    }
+}
+
+fn solve_generic<P: PyDual>(py: Python, a: &PyAny, b: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let (shape, b) = extract_array::<P>(b)?;
+    let k = match shape.as_slice() {
+        &[m] if m == n => 1,
+        &[m, k] if m == n => k,
+        _ => {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "b has shape {:?}, but the matrix has shape [{}, {}]",
+                shape, n, n
+            )))
+        }
+    };
+    let lu = LU::new(a, n).ok_or_else(singular)?;
+    let mut x = vec![P::Data::from(0.0); n * k];
+    for j in 0..k {
+        let column: Vec<_> = (0..n).map(|i| b[i * k + j]).collect();
+        for (i, xi) in lu.solve(&column).into_iter().enumerate() {
+            x[i * k + j] = xi;
+        }
+    }
+    P::to_array(py, x, &shape)
+}
+
+fn inv_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let lu = LU::new(a, n).ok_or_else(singular)?;
+    P::to_array(py, lu.inverse(), &[n, n])
+}
+
+fn det_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let det = match LU::new(a.clone(), n) {
+        Some(lu) => lu.det(),
+        None => det_division_free(&a, n),
+    };
+    Ok(P::from(det).into_py(py))
+}
+
+fn cholesky_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let chol = Cholesky::new(&a, n)
+        .ok_or_else(|| PyErr::new::<PyValueError, _>("the matrix is not positive definite!"))?;
+    P::to_array(py, chol.into_lower(), &[n, n])
+}
+
+fn lu_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let (p, l, u) = LU::new(a, n).ok_or_else(singular)?.factors();
+    Ok((
+        <f64 as PyDual>::to_array(py, p, &[n, n])?,
+        P::to_array(py, l, &[n, n])?,
+        P::to_array(py, u, &[n, n])?,
+    )
+        .to_object(py))
+}
+
+fn eigh_generic<P: PyDual>(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let (n, a) = extract_square_matrix::<P>(a)?;
+    let a: Vec<_> = (0..n * n)
+        .map(|k| {
+            let (i, j) = (k / n, k % n);
+            a[i.max(j) * n + i.min(j)]
+        })
+        .collect();
+    let (w, v) = eigh_dual(&a, n, P::first_derivative);
+    Ok((P::to_array(py, w, &[n])?, P::to_array(py, v, &[n, n])?).to_object(py))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a, b)")]
+/// Solve the linear system a x = b.
+///
+/// Parameters
+/// ----------
+/// a : array_like
+///     Square matrix of real or (hyper) dual numbers.
+/// b : array_like
+///     Right-hand side with shape (n,) or (n, k).
+///
+/// Returns
+/// -------
+/// numpy.ndarray
+///     Solution with the shape of `b`.
+///
+/// Examples
+///
+/// >>> from dualnum import derive1
+/// >>> from dualnum.linalg import solve
+/// >>> t = derive1(2.0)
+/// >>> x = solve([[t, 1.0], [1.0, 3.0]], [1.0, 2.0])
+pub fn solve(py: Python, a: &PyAny, b: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a, b], solve_generic(py, a, b))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a)")]
+/// Inverse of a square matrix of real or (hyper) dual numbers.
+pub fn inv(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a], inv_generic(py, a))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a)")]
+/// Determinant of a square matrix of real or (hyper) dual numbers.
+///
+/// The determinant is calculated from the LU decomposition with pivots
+/// chosen by the real parts. If the real part is singular (or contains
+/// non-finite numbers), a division-free algorithm is used instead, so that
+/// the derivatives are exact also if the real part of the determinant
+/// vanishes and NaN and infinities are propagated.
+///
+/// Examples
+///
+/// >>> from dualnum import derive1
+/// >>> from dualnum.linalg import det
+/// >>> det([[derive1(0.0), 1.0], [0.0, 1.0]]).first_derivative
+/// 1.0
+pub fn det(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a], det_generic(py, a))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a)")]
+/// Cholesky decomposition a = L L^T of a symmetric positive definite matrix.
+///
+/// Returns the lower triangular matrix L. Only the lower triangle of `a`
+/// is used.
+pub fn cholesky(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a], cholesky_generic(py, a))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a)")]
+/// LU decomposition a = P L U with partial pivoting.
+///
+/// The pivots are chosen by the real parts of the entries. Returns the
+/// permutation matrix P (floats), the unit lower triangular matrix L and
+/// the upper triangular matrix U.
+pub fn lu(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a], lu_generic(py, a))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a)")]
+/// Eigenvalues and eigenvectors of a symmetric matrix of real or (hyper) dual numbers.
+///
+/// The eigenvalues and eigenvectors of the real parts are calculated with
+/// the Jacobi method. Their derivatives follow from further Jacobi rotations
+/// evaluated with dual numbers, which is equivalent to (higher order)
+/// perturbation theory. Only the lower triangle of `a` is used.
+///
+/// For degenerate eigenvalues the eigenvectors within the degenerate
+/// subspace are chosen to diagonalize the first derivative of the matrix
+/// (degenerate perturbation theory), so that the eigenvalues of a matrix
+/// that depends on a single variable have the exact first derivatives.
+/// Higher derivatives within a degenerate subspace and dual numbers with
+/// several directions (e.g. gradients) are not covered: then the
+/// eigenvectors of the real matrix are kept and only the sum of the
+/// degenerate eigenvalues has exact derivatives.
+///
+/// Parameters
+/// ----------
+/// a : array_like
+///     Symmetric square matrix of real or (hyper) dual numbers.
+///
+/// Returns
+/// -------
+/// w : numpy.ndarray
+///     Eigenvalues in ascending order of their real parts.
+/// v : numpy.ndarray
+///     Normalized eigenvectors, the column v[:, i] belongs to w[i].
+///
+/// Examples
+///
+/// >>> from dualnum import derive1, eigh
+/// >>> t = derive1(1.0)
+/// >>> w, v = eigh([[2.0 * t, t], [t, 3.0]])
+pub fn eigh(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    dispatch_arrays!(&[a], eigh_generic(py, a))
+}
+
+/// Submodule `dualnum.linalg`.
+pub fn linalg(py: Python) -> PyResult<&PyModule> {
+    let m = PyModule::new(py, "linalg")?;
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(inv, m)?)?;
+    m.add_function(wrap_pyfunction!(det, m)?)?;
+    m.add_function(wrap_pyfunction!(cholesky, m)?)?;
+    m.add_function(wrap_pyfunction!(lu, m)?)?;
+    m.add_function(wrap_pyfunction!(eigh, m)?)?;
+    Ok(m)
+}
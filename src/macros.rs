@@ -1,5 +1,8 @@
 macro_rules! impl_dual_num {
     ($py_type_name:ty, $data_type:ty, $field_type:ty) => {
+        impl_dual_num!($py_type_name, $data_type, $field_type, |_| None);
+    };
+    ($py_type_name:ty, $data_type:ty, $field_type:ty, $first_derivative:expr) => {
         impl From<$data_type> for $py_type_name {
             fn from(d: $data_type) -> Self {
                 Self { _data: d }
@@ -12,12 +15,22 @@ macro_rules! impl_dual_num {
             }
         }
 
+        impl crate::coerce::PyDual for $py_type_name {
+            type Data = $data_type;
+
+            fn first_derivative(x: &$data_type) -> Option<f64> {
+                let f: fn(&$data_type) -> Option<f64> = $first_derivative;
+                f(x)
+            }
+        }
+
         #[pymethods]
         impl $py_type_name {
             #[staticmethod]
             /// (Hyper) dual number from real part, setting all other parts to zero.
-            pub fn from_re(re: $field_type) -> Self {
-                <$data_type>::from_re(re.into()).into()
+            pub fn from_re(re: &PyAny) -> PyResult<Self> {
+                let re = <$field_type as crate::coerce::FromPyReal>::from_py_real(re)?;
+                Ok(<$data_type>::from_re(re.into()).into())
             }
 
             #[getter]
@@ -212,94 +225,128 @@ macro_rules! impl_dual_num {
             fn mul_add(&self, a: Self, b: Self) -> Self {
                 self._data.mul_add(a._data, b._data).into()
             }
+
+            /// Evaluate numpy functions called with (hyper) dual numbers.
+            fn __array_function__(
+                &self,
+                py: Python,
+                func: &PyAny,
+                _types: &PyAny,
+                args: &pyo3::types::PyTuple,
+                kwargs: &pyo3::types::PyDict,
+            ) -> PyResult<PyObject> {
+                crate::array_function::array_function::<Self>(py, func, args, kwargs)
+            }
         }
 
         #[pyproto]
         impl PyNumberProtocol for $py_type_name {
-            fn __add__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<Self> {
-                if let Ok(r) = rhs.extract::<f64>() {
-                    return Ok((lhs._data + r).into());
-                };
+            fn __add__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                let py = rhs.py();
                 if let Ok(r) = rhs.extract::<Self>() {
-                    return Ok((lhs._data + r._data).into());
+                    return Ok(Self::from(lhs._data + r._data).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                if let Ok(r) = crate::coerce::extract_real(rhs) {
+                    return Ok(Self::from(lhs._data + r).into_py(py));
+                };
+                Ok(py.NotImplemented())
             }
 
-            fn __radd__(&self, other: &PyAny) -> PyResult<Self> {
-                if let Ok(o) = other.extract::<f64>() {
-                    return Ok((self._data + o).into());
+            fn __radd__(&self, other: &PyAny) -> PyResult<PyObject> {
+                let py = other.py();
+                if let Ok(o) = crate::coerce::extract_real(other) {
+                    return Ok(Self::from(self._data + o).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                Ok(py.NotImplemented())
             }
 
-            fn __sub__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<Self> {
-                if let Ok(r) = rhs.extract::<f64>() {
-                    return Ok((lhs._data - r).into());
-                };
+            fn __sub__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                let py = rhs.py();
                 if let Ok(r) = rhs.extract::<Self>() {
-                    return Ok((lhs._data - r._data).into());
+                    return Ok(Self::from(lhs._data - r._data).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                if let Ok(r) = crate::coerce::extract_real(rhs) {
+                    return Ok(Self::from(lhs._data - r).into_py(py));
+                };
+                Ok(py.NotImplemented())
             }
 
-            fn __rsub__(&self, other: &PyAny) -> PyResult<Self> {
-                if let Ok(o) = other.extract::<f64>() {
-                    return Ok((-self._data + o).into());
+            fn __rsub__(&self, other: &PyAny) -> PyResult<PyObject> {
+                let py = other.py();
+                if let Ok(o) = crate::coerce::extract_real(other) {
+                    return Ok(Self::from(-self._data + o).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                Ok(py.NotImplemented())
             }
 
-            fn __mul__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<Self> {
-                if let Ok(r) = rhs.extract::<f64>() {
-                    return Ok((lhs._data * r).into());
-                };
+            fn __mul__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                let py = rhs.py();
                 if let Ok(r) = rhs.extract::<Self>() {
-                    return Ok((lhs._data * r._data).into());
+                    return Ok(Self::from(lhs._data * r._data).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                if let Ok(r) = crate::coerce::extract_real(rhs) {
+                    return Ok(Self::from(lhs._data * r).into_py(py));
+                };
+                Ok(py.NotImplemented())
             }
 
-            fn __rmul__(&self, other: &PyAny) -> PyResult<Self> {
-                if let Ok(o) = other.extract::<f64>() {
-                    return Ok((self._data * o).into());
+            fn __rmul__(&self, other: &PyAny) -> PyResult<PyObject> {
+                let py = other.py();
+                if let Ok(o) = crate::coerce::extract_real(other) {
+                    return Ok(Self::from(self._data * o).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                Ok(py.NotImplemented())
             }
 
-            fn __truediv__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<Self> {
-                if let Ok(r) = rhs.extract::<f64>() {
-                    return Ok((lhs._data / r).into());
-                };
+            fn __truediv__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                let py = rhs.py();
                 if let Ok(r) = rhs.extract::<Self>() {
-                    return Ok((lhs._data / r._data).into());
+                    return Ok(Self::from(lhs._data / r._data).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
-            }
-
-            fn __rtruediv__(&self, other: &PyAny) -> PyResult<Self> {
-                if let Ok(o) = other.extract::<f64>() {
-                    return Ok((self._data.recip() * o).into());
+                if let Ok(r) = crate::coerce::extract_real(rhs) {
+                    return Ok(Self::from(lhs._data / r).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                Ok(py.NotImplemented())
             }
 
-            fn __pow__(lhs: &PyAny, rhs: &PyAny, _mod: Option<u32>) -> PyResult<Self> {
-                if let (Ok(l), Ok(r)) = (lhs.extract::<Self>(), rhs.extract::<i32>()) {
-                    return Ok(l._data.powi(r).into());
+            fn __rtruediv__(&self, other: &PyAny) -> PyResult<PyObject> {
+                let py = other.py();
+                if let Ok(o) = crate::coerce::extract_real(other) {
+                    return Ok(Self::from(self._data.recip() * o).into_py(py));
                 };
-                if let (Ok(l), Ok(r)) = (lhs.extract::<Self>(), rhs.extract::<f64>()) {
-                    return Ok(l._data.powf(r).into());
+                Ok(py.NotImplemented())
+            }
+
+            fn __pow__(lhs: &PyAny, rhs: &PyAny, _mod: Option<u32>) -> PyResult<PyObject> {
+                let py = lhs.py();
+                if let Ok(l) = lhs.extract::<Self>() {
+                    if let Ok(r) = rhs.extract::<Self>() {
+                        return Ok(Self::from(l._data.powd(&r._data)).into_py(py));
+                    };
+                    if let Some(r) = crate::coerce::extract_exponent(rhs)? {
+                        return Ok(Self::from(l._data.powi(r)).into_py(py));
+                    };
+                    if let Ok(r) = crate::coerce::extract_real(rhs) {
+                        return Ok(Self::from(l._data.powf(r)).into_py(py));
+                    };
                 };
-                if let (Ok(l), Ok(r)) = (lhs.extract::<Self>(), rhs.extract::<Self>()) {
-                    return Ok(l._data.powd(&r._data).into());
+                if let (Ok(l), Ok(r)) = (crate::coerce::extract_real(lhs), rhs.extract::<Self>()) {
+                    return Ok(Self::from(<$data_type>::from(l).powd(&r._data)).into_py(py));
                 };
-                Err(PyErr::new::<PyTypeError, _>(format!("not implemented!")))
+                Ok(py.NotImplemented())
             }
 
             fn __neg__(&self) -> PyResult<Self> {
                 Ok((-self._data).into())
             }
+
+            fn __abs__(&self) -> PyResult<Self> {
+                if self._data.re() < 0.0 {
+                    Ok((-self._data).into())
+                } else {
+                    Ok(self._data.into())
+                }
+            }
         }
 
         #[pyproto]
@@ -310,3 +357,458 @@ macro_rules! impl_dual_num {
         }
     };
 }
+
+macro_rules! impl_dual_array {
+    ($py_type_name:ident, $py_name:literal, $data_type:ty, $py_scalar_type:ty) => {
+        impl From<DualArray<$data_type>> for $py_type_name {
+            fn from(d: DualArray<$data_type>) -> Self {
+                Self { _data: d }
+            }
+        }
+
+        impl $py_type_name {
+            fn binary_op(
+                &self,
+                other: &PyAny,
+                op: impl Fn($data_type, $data_type) -> $data_type,
+            ) -> PyResult<PyObject> {
+                let py = other.py();
+                let res = if let Ok(o) = other.extract::<PyRef<Self>>() {
+                    self._data.zip(py, &o._data, op)?
+                } else if let Ok(o) = other.extract::<$py_scalar_type>() {
+                    self._data.map(|x| op(x, o._data))
+                } else if let Ok(o) = extract_real(other) {
+                    self._data.map(|x| op(x, <$data_type>::from(o)))
+                } else if let Ok(o) = Variables::extract(other) {
+                    self._data.zip(py, &DualArray::from_re(o.shape(), o.into_values()), op)?
+                } else {
+                    return Ok(py.NotImplemented());
+                };
+                Ok(Self::from(res).into_py(py))
+            }
+        }
+
+        #[pymethods]
+        impl $py_type_name {
+            #[staticmethod]
+            /// Dual array from real parts, setting all other parts to zero.
+            pub fn from_re(re: &PyAny) -> PyResult<Self> {
+                let re = Variables::extract(re)?;
+                Ok(DualArray::from_re(re.shape(), re.into_values()).into())
+            }
+
+            #[staticmethod]
+            /// Dual array from real parts, setting all first derivatives to one.
+            pub fn derive(x: &PyAny) -> PyResult<Self> {
+                let x = Variables::extract(x)?;
+                Ok(DualArray::derive(x.shape(), x.into_values()).into())
+            }
+
+            #[getter]
+            /// Real part.
+            fn get_value<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArrayDyn<f64>> {
+                self._data.part(py, 0)
+            }
+
+            #[getter]
+            /// Shape of the array.
+            fn get_shape<'py>(&self, py: Python<'py>) -> &'py PyTuple {
+                PyTuple::new(py, &self._data.shape)
+            }
+
+            /// Numpy (object) array of the individual dual numbers.
+            fn to_object_array(&self, py: Python) -> PyResult<PyObject> {
+                let x: Vec<_> = (0..self._data.len())
+                    .map(|i| <$py_scalar_type>::from(self._data.get(i)).into_py(py))
+                    .collect();
+                let array = PyArray1::from_vec(py, x).reshape(self._data.shape.as_slice())?;
+                crate::array_function::object_array(py, array.to_object(py).into_ref(py))
+            }
+
+            /// Sum of all elements.
+            fn sum(&self) -> $py_scalar_type {
+                (0..self._data.len())
+                    .fold(<$data_type>::from(0.0), |acc, i| acc + self._data.get(i))
+                    .into()
+            }
+
+            #[inline]
+            /// Power using 32-bit integer as exponent.
+            pub fn powi(&self, n: i32) -> Self {
+                self._data.map(|x| x.powi(n)).into()
+            }
+
+            #[inline]
+            /// Power using 64-bin float as exponent.
+            pub fn powf(&self, n: f64) -> Self {
+                self._data.map(|x| x.powf(n)).into()
+            }
+
+            #[inline]
+            /// Power using dual array as exponent.
+            pub fn powd(&self, py: Python, n: PyRef<Self>) -> PyResult<Self> {
+                Ok(self._data.zip(py, &n._data, |x, n| x.powd(&n))?.into())
+            }
+
+            #[inline]
+            /// Calculate logarithm with given base.
+            pub fn log_base(&self, base: f64) -> Self {
+                self._data.map(|x| x.log(base)).into()
+            }
+
+            #[inline]
+            /// Simultaneously computes the sine and cosine.
+            pub fn sin_cos(&self) -> (Self, Self) {
+                (
+                    self._data.map(|x| x.sin()).into(),
+                    self._data.map(|x| x.cos()).into(),
+                )
+            }
+
+            #[inline]
+            /// Reciprocal value of self.
+            pub fn recip(&self) -> Self {
+                self._data.map(|x| x.recip()).into()
+            }
+
+            #[inline]
+            /// Sqaure root.
+            pub fn sqrt(&self) -> Self {
+                self._data.map(|x| x.sqrt()).into()
+            }
+
+            #[inline]
+            /// Cubic root.
+            pub fn cbrt(&self) -> Self {
+                self._data.map(|x| x.cbrt()).into()
+            }
+
+            #[inline]
+            /// Calculate the exponential of (hyper) dual number.
+            pub fn exp(&self) -> Self {
+                self._data.map(|x| x.exp()).into()
+            }
+
+            #[inline]
+            /// Calculate 2**x of (hyper) dual number x.
+            pub fn exp2(&self) -> Self {
+                self._data.map(|x| x.exp2()).into()
+            }
+
+            #[inline]
+            /// Calculate exp(x) - 1.
+            pub fn expm1(&self) -> Self {
+                self._data.map(|x| x.exp_m1()).into()
+            }
+
+            #[inline]
+            /// Calculate natural logarithm.
+            pub fn log(&self) -> Self {
+                self._data.map(|x| x.ln()).into()
+            }
+
+            #[inline]
+            /// Calculate logarithm with base 2.
+            pub fn log2(&self) -> Self {
+                self._data.map(|x| x.log2()).into()
+            }
+
+            #[inline]
+            /// Calculate logarithm with base 10.
+            pub fn log10(&self) -> Self {
+                self._data.map(|x| x.log10()).into()
+            }
+
+            #[inline]
+            /// Returns ln(1+n) (natural logarithm) more accurately than if the operations were performed separately.
+            pub fn log1p(&self) -> Self {
+                self._data.map(|x| x.ln_1p()).into()
+            }
+
+            #[inline]
+            /// Sine function.
+            pub fn sin(&self) -> Self {
+                self._data.map(|x| x.sin()).into()
+            }
+
+            #[inline]
+            /// Cosine function.
+            pub fn cos(&self) -> Self {
+                self._data.map(|x| x.cos()).into()
+            }
+
+            #[inline]
+            /// Computes the tangent (in radians).
+            pub fn tan(&self) -> Self {
+                self._data.map(|x| x.tan()).into()
+            }
+
+            #[inline]
+            /// Computes the arcsine.
+            pub fn arcsin(&self) -> Self {
+                self._data.map(|x| x.asin()).into()
+            }
+
+            #[inline]
+            /// Computes the arccosine.
+            pub fn arccos(&self) -> Self {
+                self._data.map(|x| x.acos()).into()
+            }
+
+            #[inline]
+            /// Computes the arctangent.
+            pub fn arctan(&self) -> Self {
+                self._data.map(|x| x.atan()).into()
+            }
+
+            #[inline]
+            /// Computes the hyperbolic sine.
+            pub fn sinh(&self) -> Self {
+                self._data.map(|x| x.sinh()).into()
+            }
+
+            #[inline]
+            /// Computes the hyperbolic cosine.
+            pub fn cosh(&self) -> Self {
+                self._data.map(|x| x.cosh()).into()
+            }
+
+            #[inline]
+            /// Computes the hyperbolic tangent.
+            pub fn tanh(&self) -> Self {
+                self._data.map(|x| x.tanh()).into()
+            }
+
+            #[inline]
+            /// Computes the inverse hyperbolic sine.
+            pub fn arcsinh(&self) -> Self {
+                self._data.map(|x| x.asinh()).into()
+            }
+
+            #[inline]
+            /// Computes the inverse hyperbolic cosine.
+            pub fn arccosh(&self) -> Self {
+                self._data.map(|x| x.acosh()).into()
+            }
+
+            #[inline]
+            /// Computes the inverse hyperbolic tangent.
+            pub fn arctanh(&self) -> Self {
+                self._data.map(|x| x.atanh()).into()
+            }
+
+            #[inline]
+            /// Computes the first spherical bessel function.
+            pub fn sph_j0(&self) -> Self {
+                self._data.map(|x| x.sph_j0()).into()
+            }
+
+            #[inline]
+            /// Computes the second spherical bessel function.
+            pub fn sph_j1(&self) -> Self {
+                self._data.map(|x| x.sph_j1()).into()
+            }
+
+            #[inline]
+            /// Computes the third spherical bessel function.
+            pub fn sph_j2(&self) -> Self {
+                self._data.map(|x| x.sph_j2()).into()
+            }
+
+            #[inline]
+            #[pyo3(text_signature = "($self, a, b)")]
+            /// Fused multiply-add. Computes (self * a) + b with only one rounding error.
+            fn mul_add(&self, py: Python, a: PyRef<Self>, b: PyRef<Self>) -> PyResult<Self> {
+                Ok(self._data.zip3(py, &a._data, &b._data, |x, a, b| x.mul_add(a, b))?.into())
+            }
+
+            #[classattr]
+            /// Make numpy defer binary operators with arrays to this class.
+            fn __array_priority__() -> f64 {
+                100.0
+            }
+
+            #[args(inputs = "*", kwargs = "**")]
+            /// Evaluate numpy ufuncs elementwise in Rust.
+            ///
+            /// Binary arithmetic ufuncs (also used by the operators of numpy
+            /// arrays) broadcast the operands and return a dual array instead
+            /// of an object array. Unary ufuncs call the method of the same
+            /// name. Other ufuncs and methods are not supported.
+            fn __array_ufunc__(
+                slf: &PyCell<Self>,
+                ufunc: &PyAny,
+                method: &str,
+                inputs: &PyTuple,
+                kwargs: Option<&pyo3::types::PyDict>,
+            ) -> PyResult<PyObject> {
+                let py = slf.py();
+                let name: &str = ufunc.getattr("__name__")?.extract()?;
+                if method != "__call__" || kwargs.map_or(false, |k| !k.is_empty()) {
+                    return Ok(py.NotImplemented());
+                }
+                match inputs.len() {
+                    1 => match name {
+                        "negative" => Ok(Self::from(slf.borrow()._data.map(|x| -x)).into_py(py)),
+                        "reciprocal" => Ok(slf.borrow().recip().into_py(py)),
+                        "sqrt" | "cbrt" | "exp" | "exp2" | "expm1" | "log" | "log2" | "log10"
+                        | "log1p" | "sin" | "cos" | "tan" | "arcsin" | "arccos" | "arctan"
+                        | "sinh" | "cosh" | "tanh" | "arcsinh" | "arccosh" | "arctanh" => {
+                            Ok(slf.call_method0(name)?.into_py(py))
+                        }
+                        _ => Ok(py.NotImplemented()),
+                    },
+                    2 => {
+                        let (lhs, rhs) = (inputs.get_item(0), inputs.get_item(1));
+                        let reflected = !lhs.is(slf);
+                        let (this, other) = if reflected { (rhs, lhs) } else { (lhs, rhs) };
+                        let this = this.extract::<PyRef<Self>>()?;
+                        let op: fn($data_type, $data_type) -> $data_type = match name {
+                            "add" => |a, b| a + b,
+                            "subtract" => |a, b| a - b,
+                            "multiply" => |a, b| a * b,
+                            "divide" | "true_divide" => |a, b| a / b,
+                            "power" => |a, b| a.powd(&b),
+                            _ => return Ok(py.NotImplemented()),
+                        };
+                        if reflected {
+                            this.binary_op(other, |a, b| op(b, a))
+                        } else {
+                            this.binary_op(other, op)
+                        }
+                    }
+                    _ => Ok(py.NotImplemented()),
+                }
+            }
+        }
+
+        #[pyproto]
+        impl PyNumberProtocol for $py_type_name {
+            fn __add__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                lhs.binary_op(rhs, |a, b| a + b)
+            }
+
+            fn __radd__(&self, other: &PyAny) -> PyResult<PyObject> {
+                self.binary_op(other, |a, b| b + a)
+            }
+
+            fn __sub__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                lhs.binary_op(rhs, |a, b| a - b)
+            }
+
+            fn __rsub__(&self, other: &PyAny) -> PyResult<PyObject> {
+                self.binary_op(other, |a, b| b - a)
+            }
+
+            fn __mul__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                lhs.binary_op(rhs, |a, b| a * b)
+            }
+
+            fn __rmul__(&self, other: &PyAny) -> PyResult<PyObject> {
+                self.binary_op(other, |a, b| b * a)
+            }
+
+            fn __truediv__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+                lhs.binary_op(rhs, |a, b| a / b)
+            }
+
+            fn __rtruediv__(&self, other: &PyAny) -> PyResult<PyObject> {
+                self.binary_op(other, |a, b| b / a)
+            }
+
+            fn __pow__(lhs: &PyAny, rhs: &PyAny, _mod: Option<u32>) -> PyResult<PyObject> {
+                let py = lhs.py();
+                if let Ok(l) = lhs.extract::<PyRef<Self>>() {
+                    if let Some(r) = crate::coerce::extract_exponent(rhs)? {
+                        return Ok(l.powi(r).into_py(py));
+                    };
+                    if let Ok(r) = crate::coerce::extract_real(rhs) {
+                        return Ok(l.powf(r).into_py(py));
+                    };
+                    return l.binary_op(rhs, |a, b| a.powd(&b));
+                };
+                Ok(py.NotImplemented())
+            }
+
+            fn __neg__(&self) -> PyResult<Self> {
+                Ok(self._data.map(|x| -x).into())
+            }
+        }
+
+        #[pyproto]
+        impl pyo3::class::basic::PyObjectProtocol for $py_type_name {
+            fn __repr__(&self) -> PyResult<String> {
+                Ok(format!("{}(shape={:?})", $py_name, self._data.shape))
+            }
+        }
+    };
+}
+
+/// Call the generic function `$f::<P>` with the Python dual number class `P` of `$obj`.
+///
+/// Evaluates `$default` if `$obj` is not a dual number.
+macro_rules! dispatch_dual {
+    (@types $obj:expr, $f:ident($($args:expr),*), $default:expr, [$($t:ty),*]) => {{
+        let obj: &PyAny = $obj;
+        $(if obj.is_instance::<$t>()? { $f::<$t>($($args),*) } else)* { $default }
+    }};
+    ($obj:expr, $f:ident($($args:expr),*), $default:expr) => {
+        dispatch_dual!(@types $obj, $f($($args),*), $default, [
+            crate::dual::PyDual64,
+            crate::dual::PyDual64_2,
+            crate::dual::PyDual64_3,
+            crate::dual::PyDual64_4,
+            crate::dual::PyDual64_5,
+            crate::dual::PyDual64_6,
+            crate::dual::PyDual64_7,
+            crate::dual::PyDual64_8,
+            crate::dual::PyDual64_9,
+            crate::dual::PyDual64_10,
+            crate::dual2::PyDual2_64,
+            crate::dual2::PyDual2Dual64,
+            crate::dual3::PyDual3_64,
+            crate::dual3::PyDual3Dual64,
+            crate::hyperdual::PyHyperDual64,
+            crate::hyperdual::PyHyperDualDual64,
+            crate::hyperdual::PyHyperDual64_2,
+            crate::hyperdual::PyHyperDual64_3,
+            crate::hyperdual::PyHyperDual64_4,
+            crate::hyperdual::PyHyperDual64_5,
+            crate::hyperdual::PyHyperDual64_1_2,
+            crate::hyperdual::PyHyperDual64_1_3,
+            crate::hyperdual::PyHyperDual64_1_4,
+            crate::hyperdual::PyHyperDual64_1_5,
+            crate::hyperdual::PyHyperDual64_2_1,
+            crate::hyperdual::PyHyperDual64_3_1,
+            crate::hyperdual::PyHyperDual64_4_1,
+            crate::hyperdual::PyHyperDual64_5_1,
+            crate::hyperdual::PyHyperDual64_2_2,
+            crate::hyperdual::PyHyperDual64_2_3,
+            crate::hyperdual::PyHyperDual64_2_4,
+            crate::hyperdual::PyHyperDual64_2_5,
+            crate::hyperdual::PyHyperDual64_3_2,
+            crate::hyperdual::PyHyperDual64_3_3,
+            crate::hyperdual::PyHyperDual64_3_4,
+            crate::hyperdual::PyHyperDual64_3_5,
+            crate::hyperdual::PyHyperDual64_4_2,
+            crate::hyperdual::PyHyperDual64_4_3,
+            crate::hyperdual::PyHyperDual64_4_4,
+            crate::hyperdual::PyHyperDual64_4_5,
+            crate::hyperdual::PyHyperDual64_5_2,
+            crate::hyperdual::PyHyperDual64_5_3,
+            crate::hyperdual::PyHyperDual64_5_4,
+            crate::hyperdual::PyHyperDual64_5_5
+        ])
+    };
+}
+
+/// Call `$f::<P>` with the dual number type `P` of the entries of `$arrays`
+/// or with `f64` if all entries are real.
+macro_rules! dispatch_arrays {
+    ($arrays:expr, $f:ident($($args:expr),*)) => {
+        match crate::coerce::dual_entry($arrays)? {
+            Some(x) => dispatch_dual!(x, $f($($args),*), $f::<f64>($($args),*)),
+            None => $f::<f64>($($args),*),
+        }
+    };
+}
@@ -0,0 +1,355 @@
+//! Command-line interface to calculate derivatives of expressions.
+//!
+//! Build with `cargo build --release --no-default-features --features cli`.
+use dualnum::graph::Graph;
+use dualnum::parser::parse;
+use num_dual::*;
+use serde_json::{json, Map, Value};
+use std::process;
+
+const USAGE: &str = "Calculate derivatives of mathematical expressions.
+
+Usage:
+    dualnum diff <EXPR> --at <NAME=VALUE>[,<NAME=VALUE>...] [--order <N>] [--format <FORMAT>]
+    dualnum --help
+
+Options:
+    --at <NAME=VALUE>    Value of a variable, can be given multiple times.
+    --order <N>          Highest order of derivatives, 1 to 3 for a single
+                         variable and 1 or 2 for multiple variables [default: 2].
+    --format <FORMAT>    Output format: text, json or csv [default: text].
+
+Example:
+    dualnum diff 'exp(x)/sqrt(sin(x)^3+cos(x)^3)' --at x=1.5 --order 3";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+struct Options {
+    expr: String,
+    at: Vec<(String, f64)>,
+    order: usize,
+    format: Format,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    match args.next().map(String::as_str) {
+        Some("diff") => (),
+        Some(cmd) => return Err(format!("unknown command '{}'", cmd)),
+        None => return Err(String::from("missing command")),
+    }
+    let mut expr = None;
+    let mut at = Vec::new();
+    let mut order = 2;
+    let mut format = Format::Text;
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            if expr.replace(arg.clone()).is_some() {
+                return Err(format!("unexpected argument '{}'", arg));
+            }
+            continue;
+        }
+        let (option, value) = match arg.find('=') {
+            Some(i) => (&arg[..i], arg[i + 1..].to_string()),
+            None => (
+                arg.as_str(),
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("missing value for '{}'", arg))?,
+            ),
+        };
+        match option {
+            "--at" => {
+                for assignment in value.split(',') {
+                    let mut parts = assignment.splitn(2, '=');
+                    let name = parts.next().unwrap().trim();
+                    let x = parts
+                        .next()
+                        .and_then(|x| x.trim().parse().ok())
+                        .ok_or_else(|| format!("invalid variable assignment '{}'", assignment))?;
+                    at.push((name.to_string(), x));
+                }
+            }
+            "--order" => {
+                order = value
+                    .parse()
+                    .map_err(|_| format!("invalid order '{}'", value))?
+            }
+            "--format" => {
+                format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => return Err(format!("unknown format '{}'", value)),
+                }
+            }
+            _ => return Err(format!("unknown option '{}'", option)),
+        }
+    }
+    let expr = expr.ok_or_else(|| String::from("missing expression"))?;
+    Ok(Options {
+        expr,
+        at,
+        order,
+        format,
+    })
+}
+
+/// Value and derivatives of an expression.
+///
+/// For a single variable, `derivatives` contains the derivatives up to the
+/// requested order. For multiple variables it contains the gradient and
+/// the Hessian (row by row) and for constant expressions it is empty.
+struct Derivatives {
+    variables: Vec<String>,
+    value: f64,
+    derivatives: Vec<Vec<f64>>,
+}
+
+fn evaluate(graph: &Graph, variables: Vec<String>, x: &[f64], order: usize) -> Derivatives {
+    let n = x.len();
+    if n == 0 {
+        return Derivatives {
+            variables,
+            value: graph.eval(&[] as &[f64])[0],
+            derivatives: Vec::new(),
+        };
+    }
+    if n == 1 {
+        let y = graph.eval(&[Dual3_64::new(x[0], 1.0, 0.0, 0.0)])[0];
+        let derivatives = [y.v1, y.v2, y.v3][..order].to_vec();
+        return Derivatives {
+            variables,
+            value: y.re,
+            derivatives: vec![derivatives],
+        };
+    }
+    if order >= 2 {
+        let (value, gradient, hessian) = match n {
+            2 => hessian_vec::<2>(graph, x),
+            3 => hessian_vec::<3>(graph, x),
+            4 => hessian_vec::<4>(graph, x),
+            5 => hessian_vec::<5>(graph, x),
+            _ => hessian_pairs(graph, x),
+        };
+        let mut derivatives = vec![gradient];
+        derivatives.extend(hessian);
+        return Derivatives {
+            variables,
+            value,
+            derivatives,
+        };
+    }
+    let value = graph.eval(x)[0];
+    let mut derivatives = Vec::new();
+    if order >= 1 {
+        let gradient = (0..n)
+            .map(|i| {
+                let x: Vec<_> = (0..n)
+                    .map(|k| Dual64::new_scalar(x[k], if k == i { 1.0 } else { 0.0 }))
+                    .collect();
+                graph.eval(&x)[0].eps[0]
+            })
+            .collect();
+        derivatives.push(gradient);
+    }
+    Derivatives {
+        variables,
+        value,
+        derivatives,
+    }
+}
+
+/// Value, gradient and Hessian from a single evaluation with second order
+/// dual numbers in `N` variables.
+fn hessian_vec<const N: usize>(graph: &Graph, x: &[f64]) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+    let x: Vec<_> = x
+        .iter()
+        .enumerate()
+        .map(|(k, &x)| {
+            let mut v1 = [0.0; N];
+            v1[k] = 1.0;
+            Dual2Vec64::new(x, StaticVec::new_vec(v1), StaticMat::new([[0.0; N]; N]))
+        })
+        .collect();
+    let y = graph.eval(&x)[0];
+    let hessian = y.v2.raw_data().iter().map(|row| row.to_vec()).collect();
+    (y.re, y.v1.raw_array().to_vec(), hessian)
+}
+
+/// Value, gradient and Hessian from one evaluation with hyper dual numbers
+/// per entry of the upper triangle of the Hessian.
+fn hessian_pairs(graph: &Graph, x: &[f64]) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+    let n = x.len();
+    let seed = |i: usize, j: usize| -> Vec<_> {
+        x.iter()
+            .enumerate()
+            .map(|(k, &x)| {
+                HyperDual64::new_scalar(
+                    x,
+                    if k == i { 1.0 } else { 0.0 },
+                    if k == j { 1.0 } else { 0.0 },
+                    0.0,
+                )
+            })
+            .collect()
+    };
+    let mut value = 0.0;
+    let mut gradient = vec![0.0; n];
+    let mut hessian = vec![vec![0.0; n]; n];
+    for (i, j) in (0..n).flat_map(|i| (i..n).map(move |j| (i, j))) {
+        let y = graph.eval(&seed(i, j))[0];
+        if i == j {
+            value = y.re;
+            gradient[i] = y.eps1[0];
+        }
+        hessian[i][j] = y.eps1eps2[(0, 0)];
+        hessian[j][i] = hessian[i][j];
+    }
+    (value, gradient, hessian)
+}
+
+impl Derivatives {
+    /// Names and values of all derivatives.
+    fn labeled(&self) -> Vec<(String, f64)> {
+        let v = &self.variables;
+        let mut res = vec![(String::from("f"), self.value)];
+        if v.len() == 1 {
+            for (k, &d) in self.derivatives[0].iter().enumerate() {
+                let label = match k {
+                    0 => format!("df/d{}", v[0]),
+                    _ => format!("d{o}f/d{}{o}", v[0], o = k + 1),
+                };
+                res.push((label, d));
+            }
+            return res;
+        }
+        for (k, row) in self.derivatives.iter().enumerate() {
+            for (l, &d) in row.iter().enumerate() {
+                let label = match k {
+                    0 => format!("df/d{}", v[l]),
+                    _ => format!("d2f/d{}d{}", v[k - 1], v[l]),
+                };
+                res.push((label, d));
+            }
+        }
+        res
+    }
+
+    fn to_json(&self, expr: &str, x: &[f64]) -> Value {
+        let at: Map<String, Value> = self
+            .variables
+            .iter()
+            .zip(x)
+            .map(|(v, &x)| (v.clone(), json!(x)))
+            .collect();
+        let mut res = json!({
+            "expression": expr,
+            "at": at,
+            "value": self.value,
+        });
+        if self.variables.len() == 1 {
+            res["derivatives"] = json!(self.derivatives[0]);
+        } else {
+            if let Some(gradient) = self.derivatives.first() {
+                res["gradient"] = json!(gradient);
+            }
+            if self.derivatives.len() > 1 {
+                res["hessian"] = json!(self.derivatives[1..]);
+            }
+        }
+        res
+    }
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let options = parse_args(args)?;
+    let (graph, variables) = parse(&options.expr)?;
+    for (name, _) in &options.at {
+        if !variables.contains(name) {
+            return Err(format!("variable '{}' does not appear in the expression", name));
+        }
+    }
+    let x = variables
+        .iter()
+        .map(|v| {
+            options
+                .at
+                .iter()
+                .find(|(name, _)| name == v)
+                .map(|&(_, x)| x)
+                .ok_or_else(|| format!("no value given for variable '{}'", v))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let max_order = if variables.len() == 1 { 3 } else { 2 };
+    if options.order < 1 || options.order > max_order {
+        return Err(format!(
+            "order has to be between 1 and {} for {} variables",
+            max_order,
+            variables.len()
+        ));
+    }
+    let result = evaluate(&graph, variables, &x, options.order);
+    Ok(match options.format {
+        Format::Text => result
+            .labeled()
+            .iter()
+            .map(|(label, d)| format!("{} = {}", label, d))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Json => serde_json::to_string_pretty(&result.to_json(&options.expr, &x)).unwrap(),
+        Format::Csv => {
+            let (labels, values): (Vec<_>, Vec<_>) = result
+                .variables
+                .iter()
+                .zip(&x)
+                .map(|(v, x)| (v.clone(), *x))
+                .chain(result.labeled())
+                .unzip();
+            let values: Vec<_> = values.iter().map(|x| x.to_string()).collect();
+            format!("{}\n{}", labels.join(","), values.join(","))
+        }
+    })
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    match run(&args) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        run(&args)
+    }
+
+    #[test]
+    fn constant_expression() {
+        assert_eq!(diff(&["diff", "2+3"]).unwrap(), "f = 5");
+        assert_eq!(diff(&["diff", "2+3", "--format", "csv"]).unwrap(), "f\n5");
+    }
+
+    #[test]
+    fn third_order_only_for_a_single_variable() {
+        assert!(diff(&["diff", "x^4", "--at", "x=1", "--order", "3"]).is_ok());
+        assert!(diff(&["diff", "x*y", "--at", "x=1,y=2", "--order", "3"]).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+//! Unconstrained minimization with exact gradients and Hessians from
+//! second order dual numbers.
+use crate::coerce::Variables;
+use crate::derivatives::{hessian_at, value_at};
+use crate::linalg::{dot, max_norm, norm, Cholesky};
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Sufficient decrease parameter of the Armijo condition.
+const ARMIJO: f64 = 1e-4;
+/// Smallest step length tried in the line search.
+const MIN_ALPHA: f64 = 1e-10;
+/// Smallest shift added to the diagonal of indefinite Hessians.
+const MIN_SHIFT: f64 = 1e-3;
+/// Minimum ratio of actual and predicted reduction to accept a trust-region step.
+const ETA: f64 = 0.15;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Method {
+    Newton,
+    TrustRegion,
+}
+
+impl Method {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "newton" => Ok(Self::Newton),
+            "trust-region" => Ok(Self::TrustRegion),
+            _ => Err(PyErr::new::<PyValueError, _>(format!(
+                "unknown method '{}', expected 'newton' or 'trust-region'",
+                name
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Newton => "newton",
+            Self::TrustRegion => "trust-region",
+        }
+    }
+}
+
+/// Flat matrix H + shift * I.
+fn shifted(h: &[Vec<f64>], shift: f64) -> Vec<f64> {
+    let mut a = h.concat();
+    let n = h.len();
+    for i in 0..n {
+        a[i * n + i] += shift;
+    }
+    a
+}
+
+/// Cholesky decomposition of H + shift * I with the smallest shift from the
+/// sequence 0, MIN_SHIFT, 2 MIN_SHIFT, 4 MIN_SHIFT, ... (starting at least at
+/// -min(diag(H)) + MIN_SHIFT) for which the matrix is positive definite.
+fn modified_cholesky(h: &[Vec<f64>]) -> Option<(Cholesky<f64>, f64)> {
+    let n = h.len();
+    let min_diag = (0..n).map(|i| h[i][i]).fold(f64::INFINITY, f64::min);
+    let mut shift = if min_diag > 0.0 {
+        0.0
+    } else {
+        MIN_SHIFT - min_diag
+    };
+    for _ in 0..100 {
+        if let Some(chol) = Cholesky::new(&shifted(h, shift), n) {
+            return Some((chol, shift));
+        }
+        shift = (2.0 * shift).max(MIN_SHIFT);
+    }
+    None
+}
+
+/// Approximate solution of the trust-region subproblem
+/// min g^T p + 1/2 p^T H p subject to |p| <= radius.
+///
+/// The step solves (H + shift * I) p = -g where the shift is the smallest
+/// shift that makes the matrix positive definite or, if the step is
+/// longer than the radius, is determined by Newton's method on the secular
+/// equation 1/|p(shift)| = 1/radius.
+fn trust_region_step(h: &[Vec<f64>], g: &[f64], radius: f64) -> Option<(Vec<f64>, f64)> {
+    let n = g.len();
+    let (mut chol, mut shift) = modified_cholesky(h)?;
+    let neg_g: Vec<f64> = g.iter().map(|g| -g).collect();
+    let mut p = chol.solve(&neg_g);
+    for _ in 0..20 {
+        let p_norm = norm(&p);
+        if p_norm <= radius || (p_norm - radius).abs() <= 0.01 * radius {
+            break;
+        }
+        let q_norm = norm(&chol.solve_lower(&p));
+        shift += (p_norm / q_norm).powi(2) * (p_norm - radius) / radius;
+        chol = Cholesky::new(&shifted(h, shift), n)?;
+        p = chol.solve(&neg_g);
+    }
+    let p_norm = norm(&p);
+    if p_norm > radius {
+        p.iter_mut().for_each(|p| *p *= radius / p_norm);
+    }
+    Some((p, shift))
+}
+
+#[pyclass(name = "MinimizeResult")]
+/// Result of `minimize`.
+pub struct PyMinimizeResult {
+    shape: Vec<usize>,
+    x: Vec<f64>,
+    fun: f64,
+    jac: Vec<f64>,
+    hess: Vec<Vec<f64>>,
+    method: Method,
+    converged: bool,
+    flag: String,
+    iterations: usize,
+    function_calls: usize,
+    history: Vec<(f64, f64, f64, f64)>,
+}
+
+#[pymethods]
+impl PyMinimizeResult {
+    #[getter]
+    /// Minimizer in the same structure as the initial guess.
+    pub fn get_x(&self, py: Python) -> PyResult<PyObject> {
+        if self.shape.is_empty() {
+            return Ok(self.x[0].into_py(py));
+        }
+        Ok(PyArray1::from_slice(py, &self.x)
+            .reshape(self.shape.as_slice())?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Function value at the minimizer.
+    pub fn get_fun(&self) -> f64 {
+        self.fun
+    }
+
+    #[getter]
+    /// Gradient at the minimizer.
+    pub fn get_jac<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.jac)
+    }
+
+    #[getter]
+    /// Hessian at the minimizer.
+    pub fn get_hess(&self, py: Python) -> PyResult<PyObject> {
+        let n = self.x.len();
+        Ok(PyArray1::from_vec(py, self.hess.concat())
+            .reshape([n, n])?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Name of the method.
+    pub fn get_method(&self) -> &str {
+        self.method.name()
+    }
+
+    #[getter]
+    /// Whether the maximum norm of the gradient is below the tolerance.
+    pub fn get_converged(&self) -> bool {
+        self.converged
+    }
+
+    #[getter]
+    /// Description of the cause of termination.
+    pub fn get_flag(&self) -> &str {
+        &self.flag
+    }
+
+    #[getter]
+    /// Number of iterations.
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    #[getter]
+    /// Number of evaluations of the function (with real or dual numbers).
+    pub fn get_function_calls(&self) -> usize {
+        self.function_calls
+    }
+
+    #[getter]
+    /// Iterations as list of (f(x), gradient norm, step norm, shift) where
+    /// shift is the multiple of the identity added to the Hessian.
+    pub fn get_history(&self) -> Vec<(f64, f64, f64, f64)> {
+        self.history.clone()
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyMinimizeResult {
+    fn __repr__(&self) -> PyResult<String> {
+        let mut lines = vec![
+            format!(
+                "MinimizeResult(fun={}, converged={}, flag='{}', method='{}', iterations={}, function_calls={})",
+                self.fun,
+                if self.converged { "True" } else { "False" },
+                self.flag,
+                self.method.name(),
+                self.iterations,
+                self.function_calls
+            ),
+            format!(
+                "{:<6}{:>24}{:>16}{:>16}{:>12}",
+                "iter", "f(x)", "|grad|", "|step|", "shift"
+            ),
+        ];
+        for (k, (f, g, step, shift)) in self.history.iter().enumerate() {
+            lines.push(format!(
+                "{:<6}{:>24.16e}{:>16.8e}{:>16.8e}{:>12.2e}",
+                k + 1,
+                f,
+                g,
+                step,
+                shift
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[pyfunction(
+    method = "\"newton\"",
+    gtol = "1e-8",
+    maxiter = "100",
+    initial_trust_radius = "1.0",
+    max_trust_radius = "1000.0"
+)]
+#[pyo3(
+    text_signature = "(f, x0, method='newton', gtol=1e-8, maxiter=100, initial_trust_radius=1.0, max_trust_radius=1000.0)"
+)]
+/// Minimize a scalar function using exact gradients and Hessians.
+///
+/// The gradient and the Hessian are calculated by evaluating `f` with
+/// second order dual numbers (one evaluation for up to 5 variables) or
+/// hyper dual numbers (one evaluation per pair of variables otherwise).
+/// Hessians that are not positive definite are modified by adding a
+/// multiple of the identity.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Scalar function called with the same structure as `x0`.
+/// x0 : float | list[float] | numpy.ndarray
+///     Initial guess.
+/// method : str, optional
+///     'newton' (Newton's method with backtracking line search) or
+///     'trust-region' (trust-region method with nearly exact solution of
+///     the subproblem), defaults to 'newton'.
+/// gtol : float, optional
+///     Tolerance of the maximum norm of the gradient, defaults to 1e-8.
+/// maxiter : int, optional
+///     Maximum number of iterations, defaults to 100.
+/// initial_trust_radius : float, optional
+///     Initial radius of the trust region, defaults to 1.
+/// max_trust_radius : float, optional
+///     Maximum radius of the trust region, defaults to 1000.
+///
+/// Returns
+/// -------
+/// MinimizeResult
+///     The minimizer `x`, the function value `fun`, the gradient `jac`,
+///     the Hessian `hess` and convergence diagnostics.
+///
+/// Examples
+///
+/// >>> from dualnum import minimize
+/// >>> rosen = lambda x: (1.0 - x[0])**2 + 100.0 * (x[1] - x[0]**2)**2
+/// >>> res = minimize(rosen, [-1.2, 1.0], method="trust-region")
+/// >>> res.converged
+/// True
+#[allow(clippy::too_many_arguments)]
+pub fn minimize(
+    py: Python,
+    f: &PyAny,
+    x0: &PyAny,
+    method: &str,
+    gtol: f64,
+    maxiter: usize,
+    initial_trust_radius: f64,
+    max_trust_radius: f64,
+) -> PyResult<PyMinimizeResult> {
+    let method = Method::from_name(method)?;
+    let variables = Variables::extract(x0)?;
+    let mut x = variables.values().to_vec();
+    let n = x.len();
+    if n == 0 {
+        return Err(PyErr::new::<PyValueError, _>(
+            "x0 has to contain at least one value!",
+        ));
+    }
+    if initial_trust_radius <= 0.0 || max_trust_radius < initial_trust_radius {
+        return Err(PyErr::new::<PyValueError, _>(
+            "the trust radii have to satisfy 0 < initial_trust_radius <= max_trust_radius!",
+        ));
+    }
+    // Number of evaluations of `f` required for the Hessian.
+    let hessian_calls = if n <= 5 { 1 } else { n * (n + 1) / 2 };
+
+    let (fun, jac, hess) = hessian_at(py, f, &variables, &x)?;
+    let mut result = PyMinimizeResult {
+        shape: variables.shape(),
+        x: Vec::new(),
+        fun,
+        jac,
+        hess,
+        method,
+        converged: false,
+        flag: String::from("maximum number of iterations exceeded"),
+        iterations: 0,
+        function_calls: hessian_calls,
+        history: Vec::new(),
+    };
+    let mut radius = initial_trust_radius;
+
+    loop {
+        let g_norm = max_norm(&result.jac);
+        if g_norm <= gtol {
+            result.converged = true;
+            result.flag = String::from("converged");
+            break;
+        }
+        if result.iterations == maxiter {
+            break;
+        }
+        if !result.fun.is_finite() {
+            result.flag = String::from("the function value is not finite");
+            break;
+        }
+        result.iterations += 1;
+
+        let step = match method {
+            Method::Newton => {
+                let (chol, shift) = match modified_cholesky(&result.hess) {
+                    Some(c) => c,
+                    None => {
+                        result.flag = String::from("the Hessian could not be modified");
+                        break;
+                    }
+                };
+                let p: Vec<f64> = chol.solve(&result.jac).iter().map(|p| -p).collect();
+                let slope = dot(&result.jac, &p);
+                let mut alpha = 1.0;
+                loop {
+                    let x_trial: Vec<f64> = x.iter().zip(&p).map(|(x, p)| x + alpha * p).collect();
+                    let f_trial = value_at(py, f, &variables, &x_trial)?;
+                    result.function_calls += 1;
+                    if f_trial.is_finite() && f_trial <= result.fun + ARMIJO * alpha * slope {
+                        break Some((x_trial, alpha * norm(&p), shift));
+                    }
+                    alpha *= 0.5;
+                    if alpha < MIN_ALPHA {
+                        break None;
+                    }
+                }
+            }
+            Method::TrustRegion => {
+                let (p, shift) = match trust_region_step(&result.hess, &result.jac, radius) {
+                    Some(p) => p,
+                    None => {
+                        result.flag = String::from("the Hessian could not be modified");
+                        break;
+                    }
+                };
+                let p_norm = norm(&p);
+                let hp: Vec<f64> = result.hess.iter().map(|row| dot(row, &p)).collect();
+                let predicted = -(dot(&result.jac, &p) + 0.5 * dot(&p, &hp));
+                let x_trial: Vec<f64> = x.iter().zip(&p).map(|(x, p)| x + p).collect();
+                let f_trial = value_at(py, f, &variables, &x_trial)?;
+                result.function_calls += 1;
+                let rho = if f_trial.is_finite() {
+                    (result.fun - f_trial) / predicted
+                } else {
+                    f64::NEG_INFINITY
+                };
+                if rho < 0.25 {
+                    radius = 0.25 * p_norm;
+                } else if rho > 0.75 && p_norm >= 0.99 * radius {
+                    radius = (2.0 * radius).min(max_trust_radius);
+                }
+                if rho > ETA {
+                    Some((x_trial, p_norm, shift))
+                } else {
+                    result.history.push((result.fun, g_norm, 0.0, shift));
+                    if radius <= f64::EPSILON * (1.0 + max_norm(&x)) {
+                        result.flag = String::from("the trust radius is below machine precision");
+                        break;
+                    }
+                    continue;
+                }
+            }
+        };
+        let (x_new, step_norm, shift) = match step {
+            Some(step) => step,
+            None => {
+                result.flag = String::from("the line search did not find a sufficient decrease");
+                break;
+            }
+        };
+        result.history.push((result.fun, g_norm, step_norm, shift));
+        x = x_new;
+        let (fun, jac, hess) = hessian_at(py, f, &variables, &x)?;
+        result.function_calls += hessian_calls;
+        result.fun = fun;
+        result.jac = jac;
+        result.hess = hess;
+    }
+    result.x = x;
+    Ok(result)
+}
@@ -0,0 +1,296 @@
+//! Parser for mathematical expressions.
+//!
+//! Expressions consist of numbers, variables, the operators `+`, `-`, `*`,
+//! `/`, `^` (or `**`), parentheses and function calls. All functions of the
+//! dual numbers are available under their Python names (`sqrt`, `exp`,
+//! `log`, `arcsin`, `sph_j0`, ...) as well as `ln`, `asin`, `acos`,
+//! `atan`, `asinh`, `acosh`, `atanh`, `pow(x, y)`, `log_base(x, b)` and
+//! `mul_add(a, b, c)`. The constants `pi` and `e` are predefined.
+use crate::graph::{BinaryOp, Graph, Node, UnaryOp};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Pow,
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let s: String = chars[start..i].iter().collect();
+            let x = s
+                .parse()
+                .map_err(|_| format!("invalid number '{}' at position {}", s, start))?;
+            tokens.push(Token::Number(x));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            i += 1;
+            tokens.push(match c {
+                '*' if i < chars.len() && chars[i] == '*' => {
+                    i += 1;
+                    Token::Pow
+                }
+                '^' => Token::Pow,
+                '+' | '-' | '*' | '/' => Token::Op(c),
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                _ => return Err(format!("unexpected character '{}' at position {}", c, i - 1)),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    graph: Graph,
+    variables: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            Some(t) => Err(format!("expected {:?}, found {:?}", token, t)),
+            None => Err(format!("expected {:?}, found end of expression", token)),
+        }
+    }
+
+    fn push(&mut self, node: Node) -> usize {
+        self.graph.push(node)
+    }
+
+    fn constant(&self, x: usize) -> Option<f64> {
+        match self.graph.nodes[x] {
+            Node::Const(c) => Some(c),
+            Node::Unary(UnaryOp::Neg, y) => self.constant(y).map(|c| -c),
+            _ => None,
+        }
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<usize, String> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(c)) = self.peek() {
+            let op = match c {
+                '+' => BinaryOp::Add,
+                '-' => BinaryOp::Sub,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = self.push(Node::Binary(op, lhs, rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<usize, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(c)) = self.peek() {
+            let op = match c {
+                '*' => BinaryOp::Mul,
+                '/' => BinaryOp::Div,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = self.push(Node::Binary(op, lhs, rhs));
+        }
+        Ok(lhs)
+    }
+
+    /// unary := ('+' | '-') unary | power
+    fn unary(&mut self) -> Result<usize, String> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                let x = self.unary()?;
+                Ok(self.push(Node::Unary(UnaryOp::Neg, x)))
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// power := atom ('^' unary)?
+    fn power(&mut self) -> Result<usize, String> {
+        let base = self.atom()?;
+        if self.peek() != Some(&Token::Pow) {
+            return Ok(base);
+        }
+        self.pos += 1;
+        let exponent = self.unary()?;
+        Ok(self.pow(base, exponent))
+    }
+
+    fn pow(&mut self, base: usize, exponent: usize) -> usize {
+        match self.constant(exponent) {
+            Some(n) if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 => {
+                self.push(Node::Powi(base, n as i32))
+            }
+            Some(n) => self.push(Node::Powf(base, n)),
+            None => self.push(Node::Binary(BinaryOp::Pow, base, exponent)),
+        }
+    }
+
+    /// atom := number | variable | function '(' expr (',' expr)* ')' | '(' expr ')'
+    fn atom(&mut self) -> Result<usize, String> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(self.push(Node::Const(x))),
+            Some(Token::LParen) => {
+                let x = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(x)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut args = vec![self.expr()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    args.push(self.expr()?);
+                }
+                self.expect(Token::RParen)?;
+                self.function(&name, &args)
+            }
+            Some(Token::Ident(name)) => Ok(self.variable(name)),
+            Some(t) => Err(format!("unexpected {:?}", t)),
+            None => Err(String::from("unexpected end of expression")),
+        }
+    }
+
+    fn variable(&mut self, name: String) -> usize {
+        match name.as_str() {
+            "pi" => return self.push(Node::Const(std::f64::consts::PI)),
+            "e" => return self.push(Node::Const(std::f64::consts::E)),
+            _ => (),
+        }
+        let k = match self.variables.iter().position(|v| *v == name) {
+            Some(k) => k,
+            None => {
+                self.variables.push(name);
+                self.variables.len() - 1
+            }
+        };
+        self.push(Node::Input(k))
+    }
+
+    fn function(&mut self, name: &str, args: &[usize]) -> Result<usize, String> {
+        let op = match name {
+            "ln" => Some(UnaryOp::Ln),
+            "asin" => Some(UnaryOp::Asin),
+            "acos" => Some(UnaryOp::Acos),
+            "atan" => Some(UnaryOp::Atan),
+            "asinh" => Some(UnaryOp::Asinh),
+            "acosh" => Some(UnaryOp::Acosh),
+            "atanh" => Some(UnaryOp::Atanh),
+            name => UnaryOp::from_name(name),
+        };
+        let arity = |n: usize| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{}() takes {} arguments, but {} were given",
+                    name,
+                    n,
+                    args.len()
+                ))
+            }
+        };
+        if let Some(op) = op {
+            arity(1)?;
+            return Ok(self.push(Node::Unary(op, args[0])));
+        }
+        match name {
+            "pow" | "powi" | "powf" | "powd" => {
+                arity(2)?;
+                Ok(self.pow(args[0], args[1]))
+            }
+            "log_base" => {
+                arity(2)?;
+                match self.constant(args[1]) {
+                    Some(base) => Ok(self.push(Node::Log(args[0], base))),
+                    None => {
+                        let num = self.push(Node::Unary(UnaryOp::Ln, args[0]));
+                        let den = self.push(Node::Unary(UnaryOp::Ln, args[1]));
+                        Ok(self.push(Node::Binary(BinaryOp::Div, num, den)))
+                    }
+                }
+            }
+            "mul_add" => {
+                arity(3)?;
+                Ok(self.push(Node::MulAdd(args[0], args[1], args[2])))
+            }
+            _ => Err(format!("unknown function '{}'", name)),
+        }
+    }
+}
+
+/// Parse an expression into a graph with a single output.
+///
+/// Returns the graph and the names of the variables in the order of the
+/// inputs of the graph (the order of their first appearance).
+pub fn parse(expr: &str) -> Result<(Graph, Vec<String>), String> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        graph: Graph::new(0),
+        variables: Vec::new(),
+    };
+    let output = parser.expr()?;
+    if let Some(t) = parser.peek() {
+        return Err(format!("unexpected {:?}", t));
+    }
+    let mut graph = parser.graph;
+    graph.inputs = parser.variables.len();
+    graph.outputs = vec![output];
+    Ok((graph, parser.variables))
+}
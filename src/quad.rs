@@ -0,0 +1,219 @@
+//! Adaptive Gauss-Kronrod quadrature of functions returning (dual) numbers
+//! with (dual) integration bounds.
+use crate::coerce::{extract_real, PyDual};
+use num_dual::DualNum;
+use pyo3::exceptions::{PyRuntimeWarning, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+/// Nodes of the 15-point Kronrod rule on [-1, 1] (non-negative half). The
+/// odd entries are the nodes of the 7-point Gauss rule.
+const XGK: [f64; 8] = [
+    0.991455371120812639206854697526329,
+    0.949107912342758524526189684047851,
+    0.864864423359769072789712788640926,
+    0.741531185599394439863864773280788,
+    0.586087235467691130294144845693013,
+    0.405845151377397166906606412076961,
+    0.207784955007898467600689403773245,
+    0.0,
+];
+/// Weights of the 15-point Kronrod rule.
+const WGK: [f64; 8] = [
+    0.022935322010529224963732008058970,
+    0.063092092629978553290700663189204,
+    0.104790010322250183839876322541518,
+    0.140653259715525918745189590510238,
+    0.169004726639267902826583426598550,
+    0.190350578064785409913256402421014,
+    0.204432940075298892414161999234649,
+    0.209482141084727828012999174891714,
+];
+/// Weights of the 7-point Gauss rule.
+const WG: [f64; 4] = [
+    0.129484966168869693270611432679082,
+    0.279705391489276667901467771423780,
+    0.381830050505118944950369775488975,
+    0.417959183673469387755102040816327,
+];
+
+/// `f` at a real node, where it may return a real or a dual number.
+fn call_real<P: PyDual>(f: &PyAny, x: f64) -> PyResult<P::Data> {
+    P::extract_dual(f.call1((x,))?)
+}
+
+/// `f` at a dual number.
+fn call<P: PyDual>(f: &PyAny, x: P::Data) -> PyResult<P::Data> {
+    P::extract_dual(f.call1((P::from(x),))?)
+}
+
+/// The 15-point Kronrod estimate of the integral over [lo, hi] and the
+/// difference of its real part to the 7-point Gauss estimate.
+fn kronrod<P: PyDual>(f: &PyAny, lo: f64, hi: f64) -> PyResult<(P::Data, f64)> {
+    let center = 0.5 * (lo + hi);
+    let h = 0.5 * (hi - lo);
+    let fc = call_real::<P>(f, center)?;
+    let mut kronrod = fc * WGK[7];
+    let mut gauss = fc * WG[3];
+    for (j, (&x, &w)) in XGK.iter().zip(&WGK).take(7).enumerate() {
+        let fx = call_real::<P>(f, center - h * x)? + call_real::<P>(f, center + h * x)?;
+        kronrod = kronrod + fx * w;
+        if j % 2 == 1 {
+            gauss = gauss + fx * WG[j / 2];
+        }
+    }
+    Ok((kronrod * h, ((kronrod - gauss) * h).re().abs()))
+}
+
+/// Integral from the real part of `x` to `x`.
+///
+/// With d = x - re(x), the integral d * int_0^1 f(re(x) + d s) ds is a
+/// polynomial in d whose coefficients are the derivatives of f at re(x)
+/// (the Leibniz rule to all orders). The 7-point Gauss rule integrates it
+/// exactly up to derivatives of order 13.
+fn boundary<P: PyDual>(f: &PyAny, x: P::Data) -> PyResult<P::Data> {
+    let x0 = P::Data::from(x.re());
+    let d = x - x0;
+    let zero = P::Data::from(0.0);
+    if d == zero {
+        return Ok(zero);
+    }
+    let mut integral = call::<P>(f, x0 + d * 0.5)? * WG[3];
+    for (&s, &w) in XGK.iter().skip(1).step_by(2).zip(&WG[..3]) {
+        let fs =
+            call::<P>(f, x0 + d * (0.5 * (1.0 - s)))? + call::<P>(f, x0 + d * (0.5 * (1.0 + s)))?;
+        integral = integral + fs * w;
+    }
+    Ok(integral * d * 0.5)
+}
+
+fn quad_generic<P: PyDual>(
+    py: Python,
+    f: &PyAny,
+    a: &PyAny,
+    b: &PyAny,
+    epsabs: f64,
+    epsrel: f64,
+    limit: usize,
+) -> PyResult<PyObject> {
+    let (a, b) = (P::extract_dual(a)?, P::extract_dual(b)?);
+    let (lo, hi) = (a.re(), b.re());
+    if !lo.is_finite() || !hi.is_finite() {
+        return Err(PyErr::new::<PyValueError, _>(
+            "the integration bounds have to be finite!",
+        ));
+    }
+    let (integral, error) = kronrod::<P>(f, lo, hi)?;
+    let mut intervals = vec![(lo, hi, integral, error)];
+    let (integral, error) = loop {
+        let integral = intervals
+            .iter()
+            .fold(P::Data::from(0.0), |acc, &(_, _, i, _)| acc + i);
+        let error: f64 = intervals.iter().map(|&(_, _, _, e)| e).sum();
+        if error <= epsabs.max(epsrel * integral.re().abs()) {
+            break (integral, error);
+        }
+        if intervals.len() >= limit || !error.is_finite() {
+            PyErr::warn(
+                py,
+                py.get_type::<PyRuntimeWarning>(),
+                &format!(
+                    "the estimated error {:e} exceeds the tolerance after {} subintervals",
+                    error,
+                    intervals.len()
+                ),
+                1,
+            )?;
+            break (integral, error);
+        }
+        let k = (0..intervals.len())
+            .max_by(|&i, &j| {
+                intervals[i]
+                    .3
+                    .partial_cmp(&intervals[j].3)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        let (lo, hi, _, _) = intervals.swap_remove(k);
+        let mid = 0.5 * (lo + hi);
+        for &(lo, hi) in &[(lo, mid), (mid, hi)] {
+            let (i, e) = kronrod::<P>(f, lo, hi)?;
+            intervals.push((lo, hi, i, e));
+        }
+    };
+
+    let integral = integral + boundary::<P>(f, b)? - boundary::<P>(f, a)?;
+    Ok((P::from(integral).into_py(py), error).to_object(py))
+}
+
+#[pyfunction(epsabs = "1.49e-8", epsrel = "1.49e-8", limit = "50")]
+#[pyo3(text_signature = "(f, a, b, epsabs=1.49e-8, epsrel=1.49e-8, limit=50)")]
+/// Integrate a function of real or (hyper) dual numbers from a to b.
+///
+/// The integral over the real parts of the bounds is calculated with the
+/// adaptive 15-point Gauss-Kronrod rule, where `f` is called with floats
+/// and may return dual numbers, e.g., because it depends on parameters
+/// that are dual numbers. If the bounds `a` or `b` are dual numbers, their
+/// derivatives are added according to the Leibniz rule, for which `f` is
+/// called with dual numbers close to the bounds.
+///
+/// The error estimate and the tolerances refer to the real part of the
+/// integral. A RuntimeWarning is issued if the tolerances are not reached
+/// with `limit` subintervals.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Integrand, a scalar function of a single variable.
+/// a : float | dual number
+///     Lower bound (finite).
+/// b : float | dual number
+///     Upper bound (finite).
+/// epsabs : float, optional
+///     Absolute tolerance, defaults to 1.49e-8.
+/// epsrel : float, optional
+///     Relative tolerance, defaults to 1.49e-8.
+/// limit : int, optional
+///     Maximum number of subintervals, defaults to 50.
+///
+/// Returns
+/// -------
+/// y : float | dual number
+///     The integral with the dual number type of the bounds or the integrand.
+/// abserr : float
+///     Estimate of the absolute error of the real part.
+///
+/// Examples
+///
+/// >>> from dualnum import quad, derive1
+/// >>> y, err = quad(lambda x: x**2, 0.0, derive1(3.0))
+/// >>> abs(y.first_derivative - 9.0) < 1e-12
+/// True
+pub fn quad(
+    py: Python,
+    f: &PyAny,
+    a: &PyAny,
+    b: &PyAny,
+    epsabs: f64,
+    epsrel: f64,
+    limit: usize,
+) -> PyResult<PyObject> {
+    let (lo, hi) = (extract_real(a), extract_real(b));
+    let dual = match (&lo, &hi) {
+        (Ok(lo), Ok(hi)) => {
+            let y = f.call1((0.5 * (lo + hi),))?;
+            if extract_real(y).is_ok() {
+                return quad_generic::<f64>(py, f, a, b, epsabs, epsrel, limit);
+            }
+            y
+        }
+        (Err(_), _) => a,
+        (_, Err(_)) => b,
+    };
+    dispatch_dual!(
+        dual,
+        quad_generic(py, f, a, b, epsabs, epsrel, limit),
+        Err(PyErr::new::<PyTypeError, _>(
+            "the bounds and the integrand have to be real numbers or (hyper) dual numbers!"
+        ))
+    )
+}
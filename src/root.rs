@@ -0,0 +1,544 @@
+//! Root finding for scalar functions using derivatives from dual numbers and
+//! derivatives of roots with respect to parameters.
+use crate::coerce::{extract_real, extract_real_vec, PyDual, Variables};
+use crate::derivatives::{call, value};
+use crate::dual::PyDual64;
+use crate::dual2::PyDual2_64;
+use crate::dual3::PyDual3_64;
+use num_dual::*;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Method {
+    Newton,
+    Halley,
+    Householder3,
+}
+
+impl Method {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "newton" => Ok(Self::Newton),
+            "halley" => Ok(Self::Halley),
+            "householder3" => Ok(Self::Householder3),
+            _ => Err(PyErr::new::<PyValueError, _>(format!(
+                "unknown method '{}', expected 'newton', 'halley' or 'householder3'",
+                name
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Newton => "newton",
+            Self::Halley => "halley",
+            Self::Householder3 => "householder3",
+        }
+    }
+
+    /// Function value and derivatives up to the order required by the method.
+    ///
+    /// Uses `Dual64`, `Dual2_64` or `Dual3_64`, i.e., one evaluation of `f` per iteration.
+    fn derivatives(self, py: Python, f: &PyAny, x: f64) -> PyResult<[f64; 4]> {
+        let x = Variables::Scalar(x);
+        Ok(match self {
+            Self::Newton => {
+                let y = call::<PyDual64>(py, f, &x, |_, x| Dual64::new_scalar(x, 1.0))?;
+                [y.re, y.eps[0], 0.0, 0.0]
+            }
+            Self::Halley => {
+                let y = call::<PyDual2_64>(py, f, &x, |_, x| Dual2_64::new_scalar(x, 1.0, 0.0))?;
+                [y.re, y.v1[0], y.v2[0], 0.0]
+            }
+            Self::Householder3 => {
+                let y = call::<PyDual3_64>(py, f, &x, |_, x| Dual3_64::new(x, 1.0, 0.0, 0.0))?;
+                [y.re, y.v1, y.v2, y.v3]
+            }
+        })
+    }
+
+    /// Step `x_old - x_new` of the method.
+    fn step(self, [f, df, d2f, d3f]: [f64; 4]) -> f64 {
+        match self {
+            Self::Newton => f / df,
+            Self::Halley => 2.0 * f * df / (2.0 * df * df - f * d2f),
+            Self::Householder3 => {
+                (6.0 * f * df * df - 3.0 * f * f * d2f)
+                    / (6.0 * df.powi(3) - 6.0 * f * df * d2f + f * f * d3f)
+            }
+        }
+    }
+}
+
+/// Interval in which `f` changes its sign.
+struct Bracket {
+    lower: f64,
+    upper: f64,
+    f_lower: f64,
+}
+
+impl Bracket {
+    /// Replace the end point at which `f` has the same sign as `fx` by `x`.
+    fn update(&mut self, x: f64, fx: f64) {
+        if fx.signum() == self.f_lower.signum() {
+            self.lower = x;
+            self.f_lower = fx;
+        } else {
+            self.upper = x;
+        }
+    }
+
+    fn contains(&self, x: f64) -> bool {
+        x > self.lower.min(self.upper) && x < self.lower.max(self.upper)
+    }
+
+    fn width(&self) -> f64 {
+        (self.upper - self.lower).abs()
+    }
+}
+
+#[pyclass(name = "RootResult")]
+/// Result of `root_scalar`.
+pub struct PyRootResult {
+    root: f64,
+    converged: bool,
+    flag: String,
+    method: Method,
+    iterations: usize,
+    function_calls: usize,
+    history: Vec<(f64, f64, f64, &'static str)>,
+}
+
+#[pymethods]
+impl PyRootResult {
+    #[getter]
+    /// Estimated root.
+    pub fn get_root(&self) -> f64 {
+        self.root
+    }
+
+    #[getter]
+    /// Whether the iteration converged.
+    pub fn get_converged(&self) -> bool {
+        self.converged
+    }
+
+    #[getter]
+    /// Description of the cause of termination.
+    pub fn get_flag(&self) -> &str {
+        &self.flag
+    }
+
+    #[getter]
+    /// Name of the method.
+    pub fn get_method(&self) -> &str {
+        self.method.name()
+    }
+
+    #[getter]
+    /// Number of iterations.
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    #[getter]
+    /// Number of evaluations of the function (with real or dual numbers).
+    pub fn get_function_calls(&self) -> usize {
+        self.function_calls
+    }
+
+    #[getter]
+    /// Iterates as list of (x, f(x), step, kind) where kind is the name of
+    /// the method or 'bisection'.
+    pub fn get_history(&self) -> Vec<(f64, f64, f64, &str)> {
+        self.history.clone()
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyRootResult {
+    fn __repr__(&self) -> PyResult<String> {
+        let mut lines = vec![
+            format!(
+                "RootResult(root={}, converged={}, flag='{}', method='{}', iterations={}, function_calls={})",
+                self.root,
+                if self.converged { "True" } else { "False" },
+                self.flag,
+                self.method.name(),
+                self.iterations,
+                self.function_calls
+            ),
+            format!(
+                "{:<6}{:>24}{:>16}{:>12}  {}",
+                "iter", "x", "f(x)", "step", "kind"
+            ),
+        ];
+        for (k, (x, fx, step, kind)) in self.history.iter().enumerate() {
+            lines.push(format!(
+                "{:<6}{:>24.16e}{:>16.8e}{:>12.2e}  {}",
+                k + 1,
+                x,
+                fx,
+                step,
+                kind
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[pyfunction(
+    method = "\"newton\"",
+    bracket = "None",
+    xtol = "1e-12",
+    rtol = "1e-12",
+    maxiter = "50"
+)]
+#[pyo3(
+    text_signature = "(f, x0, method='newton', bracket=None, xtol=1e-12, rtol=1e-12, maxiter=50)"
+)]
+/// Find a root of a scalar function using derivatives from dual numbers.
+///
+/// In every iteration, `f` is evaluated once with a dual number
+/// (Dual64 for 'newton', Dual2_64 for 'halley' and Dual3_64 for
+/// 'householder3') to obtain the exact derivatives required by the method.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Scalar function, called with a dual number.
+/// x0 : float
+///     Initial guess.
+/// method : str, optional
+///     'newton' (quadratic convergence), 'halley' (cubic convergence) or
+///     'householder3' (quartic convergence), defaults to 'newton'.
+/// bracket : (float, float), optional
+///     Interval in which `f` changes its sign. If given, the interval is
+///     narrowed in every iteration and bisection steps are taken whenever
+///     a step leaves the interval or is not finite.
+/// xtol : float, optional
+///     Absolute tolerance of the step size, defaults to 1e-12.
+/// rtol : float, optional
+///     Relative tolerance of the step size, defaults to 1e-12.
+/// maxiter : int, optional
+///     Maximum number of iterations, defaults to 50.
+///
+/// Returns
+/// -------
+/// RootResult
+///     The iteration is converged if f(x) = 0 or |step| <= xtol + rtol * |x|.
+///     The iterates are available in `history`.
+///
+/// Examples
+///
+/// >>> from dualnum import root_scalar
+/// >>> import numpy as np
+/// >>> res = root_scalar(lambda x: x**2 - 2.0, 1.0, method="halley")
+/// >>> np.isclose(res.root, np.sqrt(2.0))
+/// True
+#[allow(clippy::too_many_arguments)]
+pub fn root_scalar(
+    py: Python,
+    f: &PyAny,
+    x0: &PyAny,
+    method: &str,
+    bracket: Option<&PyAny>,
+    xtol: f64,
+    rtol: f64,
+    maxiter: usize,
+) -> PyResult<PyRootResult> {
+    let method = Method::from_name(method)?;
+    let mut x = extract_real(x0)?;
+    let mut result = PyRootResult {
+        root: x,
+        converged: false,
+        flag: String::from("maximum number of iterations exceeded"),
+        method,
+        iterations: 0,
+        function_calls: 0,
+        history: Vec::new(),
+    };
+
+    let mut bracket = match bracket {
+        None => None,
+        Some(bracket) => {
+            let (a, b) = match extract_real_vec(bracket)?.as_slice() {
+                &[a, b] => (a, b),
+                _ => {
+                    return Err(PyErr::new::<PyValueError, _>(
+                        "bracket has to consist of two real numbers!",
+                    ))
+                }
+            };
+            let fa = value(py, f, &Variables::Scalar(a))?;
+            let fb = value(py, f, &Variables::Scalar(b))?;
+            result.function_calls += 2;
+            for &(x, fx) in &[(a, fa), (b, fb)] {
+                if fx == 0.0 {
+                    result.root = x;
+                    result.converged = true;
+                    result.flag = String::from("converged");
+                    return Ok(result);
+                }
+            }
+            if fa.signum() == fb.signum() || fa.is_nan() || fb.is_nan() {
+                return Err(PyErr::new::<PyValueError, _>(format!(
+                    "f(a) and f(b) must have different signs, got f({}) = {} and f({}) = {}",
+                    a, fa, b, fb
+                )));
+            }
+            let bracket = Bracket {
+                lower: a,
+                upper: b,
+                f_lower: fa,
+            };
+            if !bracket.contains(x) {
+                return Err(PyErr::new::<PyValueError, _>(format!(
+                    "x0 = {} is not inside the bracket [{}, {}]",
+                    x, a, b
+                )));
+            }
+            Some(bracket)
+        }
+    };
+
+    for _ in 0..maxiter {
+        let d = method.derivatives(py, f, x)?;
+        result.iterations += 1;
+        result.function_calls += 1;
+        if d[0] == 0.0 {
+            result.history.push((x, d[0], 0.0, method.name()));
+            result.converged = true;
+            result.flag = String::from("converged");
+            break;
+        }
+        let mut x_new = x - method.step(d);
+        let mut kind = method.name();
+        if let Some(bracket) = bracket.as_mut() {
+            bracket.update(x, d[0]);
+            if !x_new.is_finite() || !bracket.contains(x_new) {
+                x_new = 0.5 * (bracket.lower + bracket.upper);
+                kind = "bisection";
+            }
+        } else if !x_new.is_finite() {
+            result.history.push((x, d[0], f64::NAN, kind));
+            result.flag = format!("step is not finite (f'(x) = {})", d[1]);
+            break;
+        }
+        let step = x - x_new;
+        result.history.push((x, d[0], step, kind));
+        x = x_new;
+        let tol = xtol + rtol * x.abs();
+        if step.abs() <= tol || bracket.as_ref().map_or(false, |b| b.width() <= tol) {
+            result.converged = true;
+            result.flag = String::from("converged");
+            break;
+        }
+    }
+    result.root = x;
+    Ok(result)
+}
+
+/// Upper bound of the number of chord iterations in `chord`.
+const MAX_ORDER: usize = 8;
+
+/// Chord iterations x <- x - f(x) / f_x for the non-real parts of x starting
+/// from the real root `x`.
+///
+/// Every iteration makes the derivatives of one more order exact, so the
+/// iterations are stopped as soon as the non-real part of f(x) vanishes.
+fn chord<T: DualNum<f64> + From<f64> + Copy + PartialEq>(
+    x: f64,
+    f_x: f64,
+    mut f: impl FnMut(T) -> PyResult<T>,
+) -> PyResult<T> {
+    let mut x = T::from(x);
+    for _ in 0..MAX_ORDER {
+        let y = f(x)?;
+        let y = y - y.re();
+        if y == T::from(0.0) {
+            break;
+        }
+        x = x - y / f_x;
+    }
+    Ok(x)
+}
+
+/// Propagate the derivatives of the parameters `p` to the root `x` of g(x, p) = 0.
+///
+/// Starting from the real root, chord iterations x <- x - g(x, p) / g_x with
+/// the real derivative g_x at the root are evaluated with dual numbers until
+/// the derivatives do not change anymore.
+fn refine_root<P: PyDual>(py: Python, g: &PyAny, x: f64, p: &PyAny) -> PyResult<PyObject> {
+    let parameters = P::extract_flat(p)?;
+    let re: Vec<f64> = parameters.iter().map(|p| p.re()).collect();
+    let p_re = match P::extract_dual(p) {
+        Ok(_) => re[0].into_py(py),
+        Err(_) => re.into_py(py),
+    };
+    let dg = PyDual64::extract_dual(g.call1((PyDual64::from(Dual64::new_scalar(x, 1.0)), p_re))?)?;
+    let g_x = dg.eps[0];
+    if g_x == 0.0 || !g_x.is_finite() {
+        return Err(PyErr::new::<PyValueError, _>(format!(
+            "the derivative of g with respect to x is {} at x = {}",
+            g_x, x
+        )));
+    }
+    let x = chord(x, g_x, |x: P::Data| {
+        P::extract_dual(g.call1((P::from(x), p))?)
+    })?;
+    Ok(P::from(x).into_py(py))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(g, x, p)")]
+/// Derivatives of the solution x(p) of g(x, p) = 0 via the implicit function theorem.
+///
+/// Given the converged (real) root `x` for the real parts of the
+/// parameters `p`, the root is returned as a dual number of the same
+/// type as `p`, i.e., with the derivatives of x(p) with respect to
+/// whatever the parameters are seeded with. Higher derivatives
+/// (Dual2_64, HyperDual64, Dual3_64, ...) are calculated as well.
+///
+/// Parameters
+/// ----------
+/// g : callable
+///     Residual g(x, p), called with a dual number `x` and `p`.
+/// x : float
+///     Root of g for the real parts of the parameters, e.g. from `root_scalar`.
+/// p : dual number | list[dual number]
+///     Parameters as (hyper) dual numbers of a single type.
+///
+/// Returns
+/// -------
+/// dual number
+///     The root with the same type as the parameters.
+///
+/// Examples
+///
+/// >>> from dualnum import implicit_root, derive2
+/// >>> x = implicit_root(lambda x, p: x**2 - p, 2.0, derive2(4.0))
+/// >>> x.first_derivative, x.second_derivative
+/// (0.25, -0.03125)
+pub fn implicit_root(py: Python, g: &PyAny, x: &PyAny, p: &PyAny) -> PyResult<PyObject> {
+    let x = extract_real(x)?;
+    let mut dual = p;
+    if extract_real(p).is_err() {
+        if let Ok(iter) = p.iter() {
+            for q in iter {
+                let q = q?;
+                if extract_real(q).is_err() {
+                    dual = q;
+                    break;
+                }
+            }
+        }
+    }
+    dispatch_dual!(
+        dual,
+        refine_root(py, g, x, p),
+        Err(PyErr::new::<PyTypeError, _>(
+            "p has to be a (hyper) dual number or a sequence of (hyper) dual numbers!"
+        ))
+    )
+}
+
+/// Real roots of the monic cubic z^3 + a z^2 + b z + c in ascending order.
+///
+/// The depressed cubic is solved with Cardano's formula (one real root) or
+/// the trigonometric method (three real roots) and the roots are polished
+/// with Newton steps.
+fn real_cubic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let discriminant = 0.25 * q * q + p * p * p / 27.0;
+    let mut t = if discriminant > 0.0 {
+        let u = (-0.5 * q - q.signum() * discriminant.sqrt()).cbrt();
+        vec![if u == 0.0 { u } else { u - p / (3.0 * u) }]
+    } else if p == 0.0 {
+        vec![0.0; 3]
+    } else {
+        let m = 2.0 * (-p / 3.0).sqrt();
+        let phi = (3.0 * q / (p * m)).max(-1.0).min(1.0).acos() / 3.0;
+        (0..3)
+            .map(|k| m * (phi - 2.0 * std::f64::consts::PI * k as f64 / 3.0).cos())
+            .collect()
+    };
+    for t in t.iter_mut() {
+        let mut z = *t - a / 3.0;
+        for _ in 0..2 {
+            let dg = (3.0 * z + 2.0 * a) * z + b;
+            if dg != 0.0 {
+                z -= (((z + a) * z + b) * z + c) / dg;
+            }
+        }
+        *t = z;
+    }
+    t.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    t
+}
+
+/// Real roots of the cubic with (dual) coefficients with the derivatives
+/// from chord iterations as in `refine_root`.
+fn cubic_roots_dual<P: PyDual>(py: Python, a: &PyAny, b: &PyAny, c: &PyAny) -> PyResult<PyObject> {
+    let (a, b, c) = (
+        P::extract_dual(a)?,
+        P::extract_dual(b)?,
+        P::extract_dual(c)?,
+    );
+    let roots = real_cubic_roots(a.re(), b.re(), c.re())
+        .into_iter()
+        .map(|z| {
+            let g_z = (3.0 * z + 2.0 * a.re()) * z + b.re();
+            let x = chord(z, g_z, |x: P::Data| Ok(((x + a) * x + b) * x + c))?;
+            Ok(P::from(x).into_py(py))
+        })
+        .collect::<PyResult<Vec<PyObject>>>()?;
+    Ok(roots.into_py(py))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(a, b, c)")]
+/// Real roots of the cubic polynomial z^3 + a z^2 + b z + c.
+///
+/// The coefficients can be real numbers or (hyper) dual numbers of a
+/// single type. The roots are calculated analytically from the real parts
+/// of the coefficients (Cardano's formula if there is one real root, the
+/// trigonometric method if there are three) and their derivatives follow
+/// from the implicit function theorem. At multiple roots the derivatives
+/// are not defined.
+///
+/// Parameters
+/// ----------
+/// a : float | dual number
+///     Coefficient of z^2.
+/// b : float | dual number
+///     Coefficient of z.
+/// c : float | dual number
+///     Constant coefficient.
+///
+/// Returns
+/// -------
+/// list
+///     The one or three real roots in ascending order with the type of the
+///     coefficients.
+///
+/// Examples
+///
+/// >>> from dualnum import cubic_roots, derive1
+/// >>> z = cubic_roots(-6.0, 11.0, derive1(-6.0))
+/// >>> len(z)
+/// 3
+pub fn cubic_roots(py: Python, a: &PyAny, b: &PyAny, c: &PyAny) -> PyResult<PyObject> {
+    match [a, b, c].iter().find(|x| extract_real(x).is_err()) {
+        Some(dual) => dispatch_dual!(
+            dual,
+            cubic_roots_dual(py, a, b, c),
+            Err(PyErr::new::<PyTypeError, _>(
+                "the coefficients have to be real numbers or (hyper) dual numbers!"
+            ))
+        ),
+        None => cubic_roots_dual::<f64>(py, a, b, c),
+    }
+}
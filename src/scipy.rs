@@ -0,0 +1,174 @@
+//! Derivatives from dual numbers as callables for scipy.optimize.
+use crate::coerce::Variables;
+use crate::derivatives::{hessian_at, hessian_product, jacobian_shaped};
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use pyo3::wrap_pyfunction;
+
+#[pyclass(name = "BoundFunction")]
+/// Function called with additional positional arguments as f(x, *args).
+struct PyBoundFunction {
+    f: PyObject,
+    args: Py<PyTuple>,
+}
+
+#[pymethods]
+impl PyBoundFunction {
+    #[call]
+    fn __call__(&self, py: Python, x: &PyAny) -> PyResult<PyObject> {
+        let mut args = vec![x.to_object(py)];
+        args.extend(self.args.as_ref(py).iter().map(|a| a.to_object(py)));
+        self.f.call1(py, PyTuple::new(py, args))
+    }
+}
+
+/// `f` with the extra arguments that scipy passes after `x`.
+fn bind<'py>(py: Python<'py>, f: &PyObject, args: &PyTuple) -> PyResult<&'py PyAny> {
+    if args.is_empty() {
+        return Ok(f.as_ref(py));
+    }
+    let bound = PyBoundFunction {
+        f: f.clone_ref(py),
+        args: args.into(),
+    };
+    Ok(PyCell::new(py, bound)?.as_ref())
+}
+
+#[pyclass(name = "Jacobian")]
+/// Jacobian of a function with the signature jac(x, *args), created by `jac`.
+pub struct PyJacobian {
+    f: PyObject,
+}
+
+#[pymethods]
+impl PyJacobian {
+    #[call]
+    #[args(args = "*")]
+    /// Gradient (for scalar functions) or Jacobian of `f` at `x`.
+    pub fn __call__(&self, py: Python, x: &PyAny, args: &PyTuple) -> PyResult<PyObject> {
+        let f = bind(py, &self.f, args)?;
+        let x = Variables::extract(x)?;
+        let (mut shape, _, jac) = jacobian_shaped(py, f, &x, x.values())?;
+        shape.extend(x.shape());
+        Ok(PyArray1::from_vec(py, jac.concat())
+            .reshape(shape.as_slice())?
+            .to_object(py))
+    }
+}
+
+#[pyclass(name = "Hessian")]
+/// Hessian of a function with the signature hess(x, *args), created by `hess`.
+pub struct PyHessian {
+    f: PyObject,
+}
+
+#[pymethods]
+impl PyHessian {
+    #[call]
+    #[args(args = "*")]
+    /// Hessian of `f` at `x`.
+    pub fn __call__(&self, py: Python, x: &PyAny, args: &PyTuple) -> PyResult<PyObject> {
+        let f = bind(py, &self.f, args)?;
+        let x = Variables::extract(x)?;
+        let (_, _, hess) = hessian_at(py, f, &x, x.values())?;
+        Ok(PyArray1::from_vec(py, hess.concat())
+            .reshape(x.shape().repeat(2).as_slice())?
+            .to_object(py))
+    }
+}
+
+#[pyclass(name = "HessianProduct")]
+/// Product of the Hessian of a function with a vector with the signature
+/// hessp(x, p, *args), created by `hessp`.
+pub struct PyHessianProduct {
+    f: PyObject,
+}
+
+#[pymethods]
+impl PyHessianProduct {
+    #[call]
+    #[args(args = "*")]
+    /// Product of the Hessian of `f` at `x` with `p`.
+    pub fn __call__(&self, py: Python, x: &PyAny, p: &PyAny, args: &PyTuple) -> PyResult<PyObject> {
+        let f = bind(py, &self.f, args)?;
+        let x = Variables::extract(x)?;
+        let p = Variables::extract(p)?.into_values();
+        if p.len() != x.values().len() {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "p has {} entries, but x has {}",
+                p.len(),
+                x.values().len()
+            )));
+        }
+        let hp = hessian_product(py, f, &x, x.values(), &p)?;
+        Ok(PyArray1::from_vec(py, hp)
+            .reshape(x.shape().as_slice())?
+            .to_object(py))
+    }
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(f)")]
+/// Gradient or Jacobian of `f` in the form expected by scipy.optimize.
+///
+/// The returned callable jac(x, *args) evaluates f(x, *args) with vector
+/// dual numbers. For scalar functions (minimize) it returns the gradient
+/// with the shape of `x`, for vector-valued functions (root,
+/// least_squares) the Jacobian with shape (m, n).
+///
+/// Examples
+///
+/// >>> from dualnum.scipy import jac
+/// >>> from scipy.optimize import minimize, rosen
+/// >>> res = minimize(rosen, [1.3, 0.7, 0.8], method="BFGS", jac=jac(rosen))
+pub fn jac(f: PyObject) -> PyJacobian {
+    PyJacobian { f }
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(f)")]
+/// Hessian of the scalar function `f` in the form expected by scipy.optimize.
+///
+/// The returned callable hess(x, *args) evaluates f(x, *args) with second
+/// order dual numbers and returns the Hessian with shape (n, n).
+///
+/// Examples
+///
+/// >>> from dualnum.scipy import jac, hess
+/// >>> from scipy.optimize import minimize, rosen
+/// >>> res = minimize(rosen, [1.3, 0.7, 0.8], method="trust-exact", jac=jac(rosen), hess=hess(rosen))
+pub fn hess(f: PyObject) -> PyHessian {
+    PyHessian { f }
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(f)")]
+/// Hessian-vector product of the scalar function `f` in the form expected by scipy.optimize.
+///
+/// The returned callable hessp(x, p, *args) evaluates f(x, *args) with
+/// hyper dual numbers seeded with `p` and returns the product of the
+/// Hessian with `p` without forming the Hessian. `f` is evaluated once for
+/// up to 5 variables and ceil(n / 5) times for n variables otherwise.
+///
+/// Examples
+///
+/// >>> from dualnum.scipy import jac, hessp
+/// >>> from scipy.optimize import minimize, rosen
+/// >>> res = minimize(rosen, [1.3, 0.7, 0.8], method="Newton-CG", jac=jac(rosen), hessp=hessp(rosen))
+pub fn hessp(f: PyObject) -> PyHessianProduct {
+    PyHessianProduct { f }
+}
+
+/// Submodule `dualnum.scipy`.
+pub fn scipy(py: Python) -> PyResult<&PyModule> {
+    let m = PyModule::new(py, "scipy")?;
+    m.add_class::<PyJacobian>()?;
+    m.add_class::<PyHessian>()?;
+    m.add_class::<PyHessianProduct>()?;
+    m.add_function(wrap_pyfunction!(jac, m)?)?;
+    m.add_function(wrap_pyfunction!(hess, m)?)?;
+    m.add_function(wrap_pyfunction!(hessp, m)?)?;
+    Ok(m)
+}
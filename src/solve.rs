@@ -0,0 +1,285 @@
+//! Newton's method for systems of nonlinear equations with Jacobians from
+//! vector dual numbers.
+use crate::coerce::Variables;
+use crate::derivatives::{jacobian, vector_at};
+use crate::linalg::{max_norm, LU};
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Sufficient decrease parameter of the Armijo condition.
+const ARMIJO: f64 = 1e-4;
+/// Smallest step length tried in the line search.
+const MIN_ALPHA: f64 = 1e-4;
+
+fn merit(y: &[f64]) -> f64 {
+    0.5 * y.iter().map(|y| y * y).sum::<f64>()
+}
+
+#[pyclass(name = "SolveResult")]
+/// Result of `solve_newton`.
+pub struct PySolveResult {
+    shape: Vec<usize>,
+    x: Vec<f64>,
+    fun: Vec<f64>,
+    jac: Vec<Vec<f64>>,
+    converged: bool,
+    flag: String,
+    iterations: usize,
+    function_calls: usize,
+    history: Vec<(f64, f64, f64)>,
+}
+
+#[pymethods]
+impl PySolveResult {
+    #[getter]
+    /// Solution in the same structure as the initial guess.
+    pub fn get_x(&self, py: Python) -> PyResult<PyObject> {
+        if self.shape.is_empty() {
+            return Ok(self.x[0].into_py(py));
+        }
+        Ok(PyArray1::from_slice(py, &self.x)
+            .reshape(self.shape.as_slice())?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Residuals at the solution.
+    pub fn get_fun<'py>(&self, py: Python<'py>) -> &'py PyArray1<f64> {
+        PyArray1::from_slice(py, &self.fun)
+    }
+
+    #[getter]
+    /// Jacobian at the solution.
+    pub fn get_jac(&self, py: Python) -> PyResult<PyObject> {
+        let n = self.x.len();
+        Ok(PyArray1::from_vec(py, self.jac.concat())
+            .reshape([self.jac.len(), n])?
+            .to_object(py))
+    }
+
+    #[getter]
+    /// Whether the maximum norm of the residuals is below the tolerance.
+    pub fn get_converged(&self) -> bool {
+        self.converged
+    }
+
+    #[getter]
+    /// Description of the cause of termination.
+    pub fn get_flag(&self) -> &str {
+        &self.flag
+    }
+
+    #[getter]
+    /// Number of Newton steps.
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    #[getter]
+    /// Number of evaluations of the function (with real or dual numbers).
+    pub fn get_function_calls(&self) -> usize {
+        self.function_calls
+    }
+
+    #[getter]
+    /// Maximum norm of the residuals at the solution.
+    pub fn get_residual_norm(&self) -> f64 {
+        max_norm(&self.fun)
+    }
+
+    #[getter]
+    /// Iterations as list of (residual norm, step norm, step length) where
+    /// the step length is the factor of the Newton step accepted by the line search.
+    pub fn get_history(&self) -> Vec<(f64, f64, f64)> {
+        self.history.clone()
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PySolveResult {
+    fn __repr__(&self) -> PyResult<String> {
+        let mut lines = vec![
+            format!(
+                "SolveResult(converged={}, flag='{}', iterations={}, function_calls={}, residual_norm={:e})",
+                if self.converged { "True" } else { "False" },
+                self.flag,
+                self.iterations,
+                self.function_calls,
+                self.get_residual_norm()
+            ),
+            format!(
+                "{:<6}{:>16}{:>16}{:>12}",
+                "iter", "|F(x)|", "|step|", "alpha"
+            ),
+        ];
+        for (k, (norm, step, alpha)) in self.history.iter().enumerate() {
+            lines.push(format!(
+                "{:<6}{:>16.8e}{:>16.8e}{:>12.4}",
+                k + 1,
+                norm,
+                step,
+                alpha
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[pyfunction(
+    tol = "1e-10",
+    xtol = "1e-14",
+    maxiter = "50",
+    line_search = "true",
+    max_step = "None"
+)]
+#[pyo3(
+    text_signature = "(f, x0, tol=1e-10, xtol=1e-14, maxiter=50, line_search=True, max_step=None)"
+)]
+/// Solve a system of nonlinear equations F(x) = 0 with Newton's method.
+///
+/// The Jacobian is calculated exactly by evaluating `f` with vector dual
+/// numbers (one evaluation for up to 10 unknowns, one evaluation per
+/// unknown otherwise). The Newton steps are globalized with a
+/// backtracking line search on 0.5 * |F(x)|^2.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Function called with the same structure as `x0` that returns one
+///     residual per unknown (as sequence or array).
+/// x0 : float | list[float] | numpy.ndarray
+///     Initial guess.
+/// tol : float, optional
+///     Tolerance of the maximum norm of the residuals, defaults to 1e-10.
+/// xtol : float, optional
+///     The iteration stops without convergence if the maximum norm of the
+///     step is below xtol * (1 + |x|), defaults to 1e-14.
+/// maxiter : int, optional
+///     Maximum number of Newton steps, defaults to 50.
+/// line_search : bool, optional
+///     Whether to use a backtracking line search, defaults to True.
+/// max_step : float, optional
+///     Maximum norm of a step. Longer Newton steps are scaled down.
+///
+/// Returns
+/// -------
+/// SolveResult
+///     The solution `x`, the residuals `fun`, the Jacobian `jac` and
+///     convergence diagnostics.
+///
+/// Examples
+///
+/// >>> from dualnum import solve_newton
+/// >>> res = solve_newton(lambda x: [x[0]**2 + x[1]**2 - 4.0, x[0] - x[1]], [1.0, 2.0])
+/// >>> res.converged
+/// True
+#[allow(clippy::too_many_arguments)]
+pub fn solve_newton(
+    py: Python,
+    f: &PyAny,
+    x0: &PyAny,
+    tol: f64,
+    xtol: f64,
+    maxiter: usize,
+    line_search: bool,
+    max_step: Option<f64>,
+) -> PyResult<PySolveResult> {
+    let variables = Variables::extract(x0)?;
+    let mut x = variables.values().to_vec();
+    let n = x.len();
+    if n == 0 {
+        return Err(PyErr::new::<PyValueError, _>(
+            "x0 has to contain at least one value!",
+        ));
+    }
+    // Number of evaluations of `f` required for the Jacobian.
+    let jacobian_calls = if n <= 10 { 1 } else { n };
+
+    let (y, jac) = jacobian(py, f, &variables, &x)?;
+    if y.len() != n {
+        return Err(PyErr::new::<PyValueError, _>(format!(
+            "f has to return one residual per unknown, got {} residuals for {} unknowns",
+            y.len(),
+            n
+        )));
+    }
+    let mut result = PySolveResult {
+        shape: variables.shape(),
+        x: Vec::new(),
+        fun: y,
+        jac,
+        converged: false,
+        flag: String::from("maximum number of iterations exceeded"),
+        iterations: 0,
+        function_calls: jacobian_calls,
+        history: Vec::new(),
+    };
+
+    loop {
+        let norm = max_norm(&result.fun);
+        if norm <= tol {
+            result.converged = true;
+            result.flag = String::from("converged");
+            break;
+        }
+        if result.iterations == maxiter {
+            break;
+        }
+        let lu = match LU::new(result.jac.concat(), n) {
+            Some(lu) => lu,
+            None => {
+                result.flag = String::from("the Jacobian is singular");
+                break;
+            }
+        };
+        let mut dx: Vec<f64> = lu.solve(&result.fun).iter().map(|d| -d).collect();
+        if let Some(max_step) = max_step {
+            let scale = max_step / max_norm(&dx);
+            if scale < 1.0 {
+                dx.iter_mut().for_each(|d| *d *= scale);
+            }
+        }
+
+        let phi = merit(&result.fun);
+        let mut alpha = 1.0;
+        let x_new = loop {
+            let x_trial: Vec<f64> = x.iter().zip(&dx).map(|(x, d)| x + alpha * d).collect();
+            if !line_search {
+                break Some(x_trial);
+            }
+            let y_trial = vector_at(py, f, &variables, &x_trial)?;
+            result.function_calls += 1;
+            let phi_trial = merit(&y_trial);
+            if phi_trial.is_finite() && phi_trial <= (1.0 - 2.0 * ARMIJO * alpha) * phi {
+                break Some(x_trial);
+            }
+            alpha *= 0.5;
+            if alpha < MIN_ALPHA {
+                break None;
+            }
+        };
+        let x_new = match x_new {
+            Some(x_new) => x_new,
+            None => {
+                result.flag = String::from("the line search did not find a sufficient decrease");
+                break;
+            }
+        };
+
+        let step = alpha * max_norm(&dx);
+        result.history.push((norm, step, alpha));
+        result.iterations += 1;
+        x = x_new;
+        let (y, jac) = jacobian(py, f, &variables, &x)?;
+        result.function_calls += jacobian_calls;
+        result.fun = y;
+        result.jac = jac;
+        if step <= xtol * (1.0 + max_norm(&x)) && max_norm(&result.fun) > tol {
+            result.flag = String::from("the step size is below xtol");
+            break;
+        }
+    }
+    result.x = x;
+    Ok(result)
+}
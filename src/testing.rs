@@ -0,0 +1,237 @@
+//! Comparison of (hyper) dual numbers in tests.
+use crate::coerce::extract_real;
+use numpy::PyArray1;
+use pyo3::exceptions::{PyAssertionError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+use pyo3::wrap_pyfunction;
+
+/// Getters of the parts of (hyper) dual numbers.
+const PARTS: [&str; 4] = [
+    "value",
+    "first_derivative",
+    "second_derivative",
+    "third_derivative",
+];
+
+/// Flatten a real number, a (nested) dual number or a sequence/array of
+/// those into labeled real components.
+fn components(obj: &PyAny, label: String, res: &mut Vec<(String, f64)>) -> PyResult<()> {
+    if let Ok(x) = extract_real(obj) {
+        res.push((label, x));
+        return Ok(());
+    }
+    if obj.hasattr("value")? && obj.hasattr("first_derivative")? {
+        for part in PARTS.iter() {
+            if obj.hasattr(*part)? {
+                let sep = if label.is_empty() { "" } else { "." };
+                components(obj.getattr(*part)?, format!("{}{}{}", label, sep, part), res)?;
+            }
+        }
+        return Ok(());
+    }
+    if obj.is_instance::<PyString>()? || obj.is_instance::<PyBytes>()? {
+        return Err(not_comparable(obj));
+    }
+    let iter = obj.iter().map_err(|_| not_comparable(obj))?;
+    for (i, x) in iter.enumerate() {
+        components(x?, format!("{}[{}]", label, i), res)?;
+    }
+    Ok(())
+}
+
+fn not_comparable(obj: &PyAny) -> PyErr {
+    let name = obj.get_type().name().unwrap_or("?");
+    PyErr::new::<PyTypeError, _>(format!("cannot compare objects of type '{}'", name))
+}
+
+fn flatten(obj: &PyAny) -> PyResult<Vec<(String, f64)>> {
+    let mut res = Vec::new();
+    components(obj, String::new(), &mut res)?;
+    Ok(res)
+}
+
+fn close(actual: f64, desired: f64, rtol: f64, atol: f64, equal_nan: bool) -> bool {
+    if actual.is_nan() || desired.is_nan() {
+        return equal_nan && actual.is_nan() && desired.is_nan();
+    }
+    actual == desired || (actual - desired).abs() <= atol + rtol * desired.abs()
+}
+
+/// Components of `actual` and `desired`, which must have the same structure.
+fn pairs(actual: &PyAny, desired: &PyAny) -> PyResult<Vec<(String, f64, f64)>> {
+    let (a, d) = (flatten(actual)?, flatten(desired)?);
+    let same = a.len() == d.len() && a.iter().zip(&d).all(|(a, d)| a.0 == d.0);
+    if !same {
+        let labels = |x: &[(String, f64)]| {
+            x.iter()
+                .map(|(l, _)| if l.is_empty() { "value" } else { l.as_str() })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        return Err(PyErr::new::<PyAssertionError, _>(format!(
+            "actual and desired have different structures\n actual:  {}\n desired: {}",
+            labels(&a),
+            labels(&d)
+        )));
+    }
+    Ok(a.into_iter()
+        .zip(d)
+        .map(|((label, a), (_, d))| (label, a, d))
+        .collect())
+}
+
+#[pyfunction(rtol = "1e-7", atol = "0.0", equal_nan = "true", err_msg = "\"\"")]
+#[pyo3(text_signature = "(actual, desired, rtol=1e-7, atol=0, equal_nan=True, err_msg='')")]
+/// Raise an AssertionError if two (hyper) dual numbers are not equal up to tolerance.
+///
+/// All parts (value and derivatives) are compared with
+/// |actual - desired| <= atol + rtol * |desired|. Works with all dual
+/// number types (including nested and vector types), real numbers and
+/// sequences and arrays of those.
+///
+/// Parameters
+/// ----------
+/// actual : dual number, float or array_like
+///     The object to check.
+/// desired : dual number, float or array_like
+///     The expected object, with the same structure as `actual`.
+/// rtol : float, optional
+///     Relative tolerance, defaults to 1e-7.
+/// atol : float, optional
+///     Absolute tolerance, defaults to 0.
+/// equal_nan : bool, optional
+///     Whether NaNs compare equal, defaults to True.
+/// err_msg : str, optional
+///     Message that is printed in case of failure.
+///
+/// Examples
+///
+/// >>> from dualnum import Dual64
+/// >>> from dualnum.testing import assert_allclose
+/// >>> assert_allclose(Dual64(1.0, 2.0), Dual64(1.0, 2.0 + 1e-10))
+pub fn assert_allclose(
+    actual: &PyAny,
+    desired: &PyAny,
+    rtol: f64,
+    atol: f64,
+    equal_nan: bool,
+    err_msg: &str,
+) -> PyResult<()> {
+    let pairs = pairs(actual, desired)?;
+    let mismatches: Vec<_> = pairs
+        .iter()
+        .filter(|(_, a, d)| !close(*a, *d, rtol, atol, equal_nan))
+        .collect();
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let width = mismatches
+        .iter()
+        .map(|(l, _, _)| l.len())
+        .max()
+        .unwrap_or(0)
+        .max(9);
+    let mut lines = vec![
+        format!("\nNot equal to tolerance rtol={}, atol={}", rtol, atol),
+        err_msg.to_string(),
+        format!("Mismatched components: {} / {}", mismatches.len(), pairs.len()),
+        format!(
+            "{:<w$}  {:>16}  {:>16}  {:>10}",
+            "component",
+            "actual",
+            "desired",
+            "abs. error",
+            w = width
+        ),
+    ];
+    for (label, a, d) in mismatches {
+        let label = if label.is_empty() { "value" } else { label };
+        lines.push(format!(
+            "{:<w$}  {:>16.8e}  {:>16.8e}  {:>10.2e}",
+            label,
+            a,
+            d,
+            (a - d).abs(),
+            w = width
+        ));
+    }
+    lines.retain(|l| !l.is_empty());
+    Err(PyErr::new::<PyAssertionError, _>(lines.join("\n")))
+}
+
+#[pyfunction(rtol = "1e-5", atol = "1e-8", equal_nan = "false")]
+#[pyo3(text_signature = "(a, b, rtol=1e-5, atol=1e-8, equal_nan=False)")]
+/// Whether two (hyper) dual numbers are equal up to tolerance in all parts.
+///
+/// For sequences or arrays of dual numbers, the comparison is done
+/// elementwise and a boolean array is returned. Objects of different types
+/// or structures (e.g. dual numbers with a different number of
+/// derivatives) are not close.
+///
+/// Parameters
+/// ----------
+/// a, b : dual number, float or array_like
+///     The objects to compare.
+/// rtol : float, optional
+///     Relative tolerance, defaults to 1e-5.
+/// atol : float, optional
+///     Absolute tolerance, defaults to 1e-8.
+/// equal_nan : bool, optional
+///     Whether NaNs compare equal, defaults to False.
+///
+/// Returns
+/// -------
+/// bool or numpy.ndarray
+pub fn isclose(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    rtol: f64,
+    atol: f64,
+    equal_nan: bool,
+) -> PyResult<PyObject> {
+    let is_scalar = |x: &PyAny| -> PyResult<bool> {
+        Ok(extract_real(x).is_ok() || (x.hasattr("value")? && x.hasattr("first_derivative")?))
+    };
+    // objects of different structure or type are not close
+    let all_close = |a: &PyAny, b: &PyAny| {
+        let (a, b) = match (flatten(a), flatten(b)) {
+            (Ok(a), Ok(b)) => (a, b),
+            _ => return false,
+        };
+        a.len() == b.len()
+            && a.iter()
+                .zip(&b)
+                .all(|((la, x), (lb, y))| la == lb && close(*x, *y, rtol, atol, equal_nan))
+    };
+    if is_scalar(a)? && is_scalar(b)? {
+        return Ok(all_close(a, b).into_py(py));
+    }
+    let np = py.import("numpy")?;
+    let (a, b) = (
+        np.call_method1("asarray", (a, "O"))?,
+        np.call_method1("asarray", (b, "O"))?,
+    );
+    let (a, b) = np
+        .call_method1("broadcast_arrays", (a, b))?
+        .extract::<(&PyAny, &PyAny)>()?;
+    let shape: Vec<usize> = a.getattr("shape")?.extract()?;
+    let res = a
+        .call_method0("ravel")?
+        .iter()?
+        .zip(b.call_method0("ravel")?.iter()?)
+        .map(|(x, y)| Ok(all_close(x?, y?)))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyArray1::from_vec(py, res)
+        .reshape(shape.as_slice())?
+        .to_object(py))
+}
+
+/// Submodule `dualnum.testing`.
+pub fn testing(py: Python) -> PyResult<&PyModule> {
+    let m = PyModule::new(py, "testing")?;
+    m.add_function(wrap_pyfunction!(assert_allclose, m)?)?;
+    m.add_function(wrap_pyfunction!(isclose, m)?)?;
+    Ok(m)
+}
@@ -0,0 +1,658 @@
+//! Tracing of Python functions into expression graphs.
+use crate::coerce::{extract_exponent, extract_real, extract_real_vec, PyDual};
+use crate::graph::{BinaryOp, Graph, Node, UnaryOp};
+use crate::parser::parse;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::number::PyNumberProtocol;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[pyclass(name = "Tracer", unsendable)]
+#[derive(Clone)]
+/// Placeholder for a variable that records all operations applied to it.
+///
+/// Tracers are created by `trace` and do not have a value. Therefore,
+/// the traced function must not branch on the values of its arguments.
+pub struct PyTracer {
+    graph: Rc<RefCell<Graph>>,
+    index: usize,
+}
+
+impl PyTracer {
+    fn push(&self, node: Node) -> Self {
+        let index = self.graph.borrow_mut().push(node);
+        Self {
+            graph: self.graph.clone(),
+            index,
+        }
+    }
+
+    fn unary(&self, op: UnaryOp) -> Self {
+        self.push(Node::Unary(op, self.index))
+    }
+
+    /// Index of a tracer of the same trace or of a new constant node.
+    fn operand(&self, obj: &PyAny) -> PyResult<Option<usize>> {
+        if let Ok(t) = obj.extract::<PyRef<Self>>() {
+            if !Rc::ptr_eq(&t.graph, &self.graph) {
+                return Err(PyErr::new::<PyValueError, _>(
+                    "tracers of different traces cannot be combined!",
+                ));
+            }
+            return Ok(Some(t.index));
+        }
+        if let Ok(c) = extract_real(obj) {
+            return Ok(Some(self.graph.borrow_mut().push(Node::Const(c))));
+        }
+        Ok(None)
+    }
+
+    fn binary(&self, other: &PyAny, op: BinaryOp, reflected: bool) -> PyResult<PyObject> {
+        let py = other.py();
+        Ok(match self.operand(other)? {
+            Some(o) if reflected => self.push(Node::Binary(op, o, self.index)).into_py(py),
+            Some(o) => self.push(Node::Binary(op, self.index, o)).into_py(py),
+            None => py.NotImplemented(),
+        })
+    }
+
+    fn expect_operand(&self, obj: &PyAny) -> PyResult<usize> {
+        self.operand(obj)?.ok_or_else(|| {
+            PyErr::new::<PyTypeError, _>("expected a tracer or a real number")
+        })
+    }
+}
+
+#[pymethods]
+impl PyTracer {
+    /// Reciprocal value of self.
+    pub fn recip(&self) -> Self {
+        self.unary(UnaryOp::Recip)
+    }
+
+    /// Power using 32-bit integer as exponent.
+    pub fn powi(&self, n: i32) -> Self {
+        self.push(Node::Powi(self.index, n))
+    }
+
+    /// Power using 64-bin float as exponent.
+    pub fn powf(&self, n: f64) -> Self {
+        self.push(Node::Powf(self.index, n))
+    }
+
+    /// Power using a traced variable as exponent.
+    pub fn powd(&self, n: &PyAny) -> PyResult<Self> {
+        let n = self.expect_operand(n)?;
+        Ok(self.push(Node::Binary(BinaryOp::Pow, self.index, n)))
+    }
+
+    /// Sqaure root.
+    pub fn sqrt(&self) -> Self {
+        self.unary(UnaryOp::Sqrt)
+    }
+
+    /// Cubic root.
+    pub fn cbrt(&self) -> Self {
+        self.unary(UnaryOp::Cbrt)
+    }
+
+    /// Exponential function.
+    pub fn exp(&self) -> Self {
+        self.unary(UnaryOp::Exp)
+    }
+
+    /// Calculate 2**x.
+    pub fn exp2(&self) -> Self {
+        self.unary(UnaryOp::Exp2)
+    }
+
+    /// Calculate exp(x) - 1.
+    pub fn expm1(&self) -> Self {
+        self.unary(UnaryOp::ExpM1)
+    }
+
+    /// Calculate natural logarithm.
+    pub fn log(&self) -> Self {
+        self.unary(UnaryOp::Ln)
+    }
+
+    /// Calculate logarithm with given base.
+    pub fn log_base(&self, base: f64) -> Self {
+        self.push(Node::Log(self.index, base))
+    }
+
+    /// Calculate logarithm with base 2.
+    pub fn log2(&self) -> Self {
+        self.unary(UnaryOp::Log2)
+    }
+
+    /// Calculate logarithm with base 10.
+    pub fn log10(&self) -> Self {
+        self.unary(UnaryOp::Log10)
+    }
+
+    /// Calculate ln(1+x).
+    pub fn log1p(&self) -> Self {
+        self.unary(UnaryOp::Ln1p)
+    }
+
+    /// Sine function.
+    pub fn sin(&self) -> Self {
+        self.unary(UnaryOp::Sin)
+    }
+
+    /// Cosine function.
+    pub fn cos(&self) -> Self {
+        self.unary(UnaryOp::Cos)
+    }
+
+    /// Tangent function.
+    pub fn tan(&self) -> Self {
+        self.unary(UnaryOp::Tan)
+    }
+
+    /// Calculate sine and cosine simultaneously.
+    pub fn sin_cos(&self) -> (Self, Self) {
+        (self.unary(UnaryOp::Sin), self.unary(UnaryOp::Cos))
+    }
+
+    /// Arcsine function.
+    pub fn arcsin(&self) -> Self {
+        self.unary(UnaryOp::Asin)
+    }
+
+    /// Arccosine function.
+    pub fn arccos(&self) -> Self {
+        self.unary(UnaryOp::Acos)
+    }
+
+    /// Arctangent function.
+    pub fn arctan(&self) -> Self {
+        self.unary(UnaryOp::Atan)
+    }
+
+    /// Hyperbolic sine function.
+    pub fn sinh(&self) -> Self {
+        self.unary(UnaryOp::Sinh)
+    }
+
+    /// Hyperbolic cosine function.
+    pub fn cosh(&self) -> Self {
+        self.unary(UnaryOp::Cosh)
+    }
+
+    /// Hyperbolic tangent function.
+    pub fn tanh(&self) -> Self {
+        self.unary(UnaryOp::Tanh)
+    }
+
+    /// Area hyperbolic sine function.
+    pub fn arcsinh(&self) -> Self {
+        self.unary(UnaryOp::Asinh)
+    }
+
+    /// Area hyperbolic cosine function.
+    pub fn arccosh(&self) -> Self {
+        self.unary(UnaryOp::Acosh)
+    }
+
+    /// Area hyperbolic tangent function.
+    pub fn arctanh(&self) -> Self {
+        self.unary(UnaryOp::Atanh)
+    }
+
+    /// 0th order spherical Bessel function of the first kind.
+    pub fn sph_j0(&self) -> Self {
+        self.unary(UnaryOp::SphJ0)
+    }
+
+    /// 1st order spherical Bessel function of the first kind.
+    pub fn sph_j1(&self) -> Self {
+        self.unary(UnaryOp::SphJ1)
+    }
+
+    /// 2nd order spherical Bessel function of the first kind.
+    pub fn sph_j2(&self) -> Self {
+        self.unary(UnaryOp::SphJ2)
+    }
+
+    /// Fused multiply-add.
+    pub fn mul_add(&self, a: &PyAny, b: &PyAny) -> PyResult<Self> {
+        let a = self.expect_operand(a)?;
+        let b = self.expect_operand(b)?;
+        Ok(self.push(Node::MulAdd(self.index, a, b)))
+    }
+}
+
+#[pyproto]
+impl PyNumberProtocol for PyTracer {
+    fn __add__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+        lhs.binary(rhs, BinaryOp::Add, false)
+    }
+
+    fn __radd__(&self, other: &PyAny) -> PyResult<PyObject> {
+        self.binary(other, BinaryOp::Add, true)
+    }
+
+    fn __sub__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+        lhs.binary(rhs, BinaryOp::Sub, false)
+    }
+
+    fn __rsub__(&self, other: &PyAny) -> PyResult<PyObject> {
+        self.binary(other, BinaryOp::Sub, true)
+    }
+
+    fn __mul__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+        lhs.binary(rhs, BinaryOp::Mul, false)
+    }
+
+    fn __rmul__(&self, other: &PyAny) -> PyResult<PyObject> {
+        self.binary(other, BinaryOp::Mul, true)
+    }
+
+    fn __truediv__(lhs: PyRef<'p, Self>, rhs: &PyAny) -> PyResult<PyObject> {
+        lhs.binary(rhs, BinaryOp::Div, false)
+    }
+
+    fn __rtruediv__(&self, other: &PyAny) -> PyResult<PyObject> {
+        self.binary(other, BinaryOp::Div, true)
+    }
+
+    fn __pow__(lhs: &PyAny, rhs: &PyAny, _mod: Option<u32>) -> PyResult<PyObject> {
+        let py = lhs.py();
+        if let Ok(l) = lhs.extract::<PyRef<Self>>() {
+            if let Some(r) = extract_exponent(rhs)? {
+                return Ok(l.powi(r).into_py(py));
+            };
+            if let Ok(r) = extract_real(rhs) {
+                return Ok(l.powf(r).into_py(py));
+            };
+            return l.binary(rhs, BinaryOp::Pow, false);
+        };
+        if let Ok(r) = rhs.extract::<PyRef<Self>>() {
+            return r.binary(lhs, BinaryOp::Pow, true);
+        }
+        Ok(py.NotImplemented())
+    }
+
+    fn __neg__(&self) -> PyResult<Self> {
+        Ok(self.unary(UnaryOp::Neg))
+    }
+
+    fn __abs__(&self) -> PyResult<Self> {
+        Ok(self.unary(UnaryOp::Abs))
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyTracer {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Tracer(node={})", self.index))
+    }
+}
+
+#[pyclass(name = "Expression")]
+#[derive(Clone, Serialize, Deserialize)]
+/// Expression graph that is evaluated in Rust.
+///
+/// Expressions are created with `trace` and can be called with real
+/// numbers, any type of (hyper) dual number, or arrays thereof.
+pub struct PyExpression {
+    #[serde(flatten)]
+    pub graph: Graph,
+    /// Whether the expression returns a single value instead of a tuple.
+    #[serde(default)]
+    pub scalar: bool,
+}
+
+#[pymethods]
+impl PyExpression {
+    #[getter]
+    /// Number of inputs.
+    pub fn get_n_inputs(&self) -> usize {
+        self.graph.inputs
+    }
+
+    #[getter]
+    /// Number of outputs.
+    pub fn get_n_outputs(&self) -> usize {
+        self.graph.outputs.len()
+    }
+
+    #[pyo3(text_signature = "($self)")]
+    /// Serialize the expression to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    #[staticmethod]
+    #[pyo3(text_signature = "(json)")]
+    /// Read an expression from JSON.
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        let expr: Self = serde_json::from_str(json)
+            .map_err(|e| PyErr::new::<PyValueError, _>(e.to_string()))?;
+        expr.graph.validate().map_err(PyErr::new::<PyValueError, _>)?;
+        Ok(expr)
+    }
+
+    #[pyo3(text_signature = "($self, x=None)")]
+    /// Render the expression in the Graphviz DOT language.
+    ///
+    /// Parameters
+    /// ----------
+    /// x : list[float], optional
+    ///     Values of the inputs. If given, the nodes are annotated with
+    ///     their values and the edges with the partial derivatives of the
+    ///     nodes with respect to their operands.
+    ///
+    /// Returns
+    /// -------
+    /// str
+    pub fn to_dot(&self, x: Option<&PyAny>) -> PyResult<String> {
+        let x = x.map(extract_real_vec).transpose()?;
+        if let Some(x) = &x {
+            if x.len() != self.graph.inputs {
+                return Err(PyErr::new::<PyValueError, _>(format!(
+                    "expected {} input values, got {}",
+                    self.graph.inputs,
+                    x.len()
+                )));
+            }
+        }
+        Ok(self.graph.to_dot(x.as_deref()))
+    }
+
+    #[args(name = "\"f\"")]
+    #[pyo3(text_signature = "($self, name)")]
+    /// Generate a Rust function that evaluates the expression.
+    ///
+    /// The function is generic over `num_dual::DualNum<f64>`, takes the
+    /// inputs as an array and returns an array of the outputs.
+    ///
+    /// Parameters
+    /// ----------
+    /// name : str, optional
+    ///     Name of the function, defaults to 'f'.
+    ///
+    /// Returns
+    /// -------
+    /// str
+    pub fn to_rust(&self, name: &str) -> PyResult<String> {
+        check_identifier(name)?;
+        Ok(crate::codegen::rust_source(&self.graph, name))
+    }
+
+    #[args(name = "\"f\"", order = "2")]
+    #[pyo3(text_signature = "($self, name, order)")]
+    /// Generate a C function that evaluates the expression and its derivatives.
+    ///
+    /// The generated function has the signature
+    /// `void name(const double *x, double *f, double *grad, double *hess)`,
+    /// where `grad` (outputs x inputs) is only present for `order >= 1` and
+    /// `hess` (outputs x inputs x inputs) only for `order == 2`. All arrays
+    /// are stored in row-major order.
+    ///
+    /// Parameters
+    /// ----------
+    /// name : str, optional
+    ///     Name of the function, defaults to 'f'.
+    /// order : int, optional
+    ///     Highest order of derivatives (0, 1 or 2), defaults to 2.
+    ///
+    /// Returns
+    /// -------
+    /// str
+    pub fn to_c(&self, name: &str, order: usize) -> PyResult<String> {
+        check_identifier(name)?;
+        if order > 2 {
+            return Err(PyErr::new::<PyValueError, _>(
+                "C code can only be generated up to second derivatives!",
+            ));
+        }
+        Ok(crate::codegen::c_source(&self.graph, name, order))
+    }
+
+    #[call]
+    #[args(args = "*")]
+    /// Evaluate the expression.
+    ///
+    /// All arguments have to be real numbers or dual numbers of the same
+    /// type, or arrays of the same shape containing those. Scalars are
+    /// broadcast to the shape of the arrays.
+    pub fn __call__(&self, py: Python, args: &PyTuple) -> PyResult<PyObject> {
+        let args: Vec<&PyAny> = args.iter().collect();
+        self.call(py, &args)
+    }
+}
+
+impl PyExpression {
+    fn call(&self, py: Python, args: &[&PyAny]) -> PyResult<PyObject> {
+        if args.len() != self.graph.inputs {
+            return Err(PyErr::new::<PyTypeError, _>(format!(
+                "expression takes {} arguments, but {} were given",
+                self.graph.inputs,
+                args.len()
+            )));
+        }
+        let np = py.import("numpy")?;
+        let mut shape: Vec<usize> = Vec::new();
+        let mut columns = Vec::with_capacity(args.len());
+        for &arg in args {
+            let a = np.call_method1("asarray", (arg,))?;
+            let s: Vec<usize> = a.getattr("shape")?.extract()?;
+            if !s.is_empty() {
+                if !shape.is_empty() && s != shape {
+                    return Err(PyErr::new::<PyValueError, _>(format!(
+                        "arguments could not be broadcast together with shapes {:?} and {:?}",
+                        shape, s
+                    )));
+                }
+                shape = s;
+            }
+            let column = a
+                .call_method0("ravel")?
+                .iter()?
+                .collect::<PyResult<Vec<&PyAny>>>()?;
+            columns.push(column);
+        }
+        let dual = columns
+            .iter()
+            .flatten()
+            .copied()
+            .find(|x| extract_real(x).is_err());
+        let outputs = match dual {
+            None => evaluate::<f64>(py, &self.graph, &columns, &shape)?,
+            Some(dual) => dispatch_dual!(
+                dual,
+                evaluate(py, &self.graph, &columns, &shape),
+                Err(PyErr::new::<PyTypeError, _>(format!(
+                    "cannot evaluate an expression for '{}'",
+                    dual.get_type().name()?
+                )))
+            )?,
+        };
+        if self.scalar {
+            Ok(outputs[0].clone_ref(py))
+        } else {
+            Ok(PyTuple::new(py, outputs).to_object(py))
+        }
+    }
+}
+
+#[pyproto]
+impl pyo3::class::basic::PyObjectProtocol for PyExpression {
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Expression(inputs={}, outputs={}, nodes={})",
+            self.graph.inputs,
+            self.graph.outputs.len(),
+            self.graph.nodes.len()
+        ))
+    }
+}
+
+fn check_identifier(name: &str) -> PyResult<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(PyErr::new::<PyValueError, _>(format!(
+            "'{}' is not a valid function name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Evaluate a graph for every point of the (flattened) inputs.
+fn evaluate<P: PyDual>(
+    py: Python,
+    graph: &Graph,
+    columns: &[Vec<&PyAny>],
+    shape: &[usize],
+) -> PyResult<Vec<PyObject>> {
+    let n = shape.iter().product();
+    let mut outputs = vec![Vec::with_capacity(n); graph.outputs.len()];
+    let mut x = Vec::with_capacity(columns.len());
+    for i in 0..n {
+        x.clear();
+        for c in columns {
+            x.push(P::extract_dual(if c.len() == 1 { c[0] } else { c[i] })?);
+        }
+        for (o, y) in outputs.iter_mut().zip(graph.eval(&x)) {
+            o.push(y);
+        }
+    }
+    outputs
+        .into_iter()
+        .map(|o| {
+            if shape.is_empty() {
+                Ok(P::from(o[0]).into_py(py))
+            } else {
+                P::to_array(py, o, shape)
+            }
+        })
+        .collect()
+}
+
+/// Index of the node that corresponds to an output of the traced function.
+fn output_index(graph: &Rc<RefCell<Graph>>, obj: &PyAny) -> PyResult<usize> {
+    if let Ok(t) = obj.extract::<PyRef<PyTracer>>() {
+        if Rc::ptr_eq(&t.graph, graph) {
+            return Ok(t.index);
+        }
+    } else if let Ok(c) = extract_real(obj) {
+        return Ok(graph.borrow_mut().push(Node::Const(c)));
+    }
+    Err(PyErr::new::<PyTypeError, _>(
+        "the traced function has to return tracers or real numbers",
+    ))
+}
+
+#[pyfunction]
+#[pyo3(text_signature = "(f, n_inputs)")]
+/// Trace a function into an expression that is evaluated in Rust.
+///
+/// The function is called once with `n_inputs` tracers that record
+/// all operations. The resulting expression can then be evaluated for
+/// any type of (hyper) dual number and any number of points without
+/// calling back into Python.
+///
+/// Parameters
+/// ----------
+/// f : callable
+///     Function of `n_inputs` arguments returning a single value or
+///     a sequence of values.
+/// n_inputs : int
+///     Number of arguments of `f`.
+///
+/// Returns
+/// -------
+/// Expression
+///
+/// Examples
+///
+/// >>> from dualnum import trace, derive1
+/// >>> import numpy as np
+/// >>> f = trace(lambda x, y: x * np.exp(y), 2)
+/// >>> f(derive1(2.0), 0.0)
+/// 2 + [1]ε
+pub fn trace(py: Python, f: &PyAny, n_inputs: usize) -> PyResult<PyExpression> {
+    let graph = Rc::new(RefCell::new(Graph::new(n_inputs)));
+    let tracers: Vec<PyObject> = (0..n_inputs)
+        .map(|index| {
+            PyTracer {
+                graph: graph.clone(),
+                index,
+            }
+            .into_py(py)
+        })
+        .collect();
+    let res = f.call1(PyTuple::new(py, tracers))?;
+    let (outputs, scalar) = match output_index(&graph, res) {
+        Ok(o) => (vec![o], true),
+        Err(e) => {
+            if res.is_instance::<PyTracer>()? || res.iter().is_err() {
+                return Err(e);
+            }
+            let outputs = res
+                .iter()?
+                .map(|r| output_index(&graph, r?))
+                .collect::<PyResult<_>>()?;
+            (outputs, false)
+        }
+    };
+    let mut graph = graph.borrow().clone();
+    graph.outputs = outputs;
+    Ok(PyExpression { graph, scalar })
+}
+
+#[pyfunction(variables = "**")]
+#[pyo3(text_signature = "(expr, **variables)")]
+/// Evaluate a mathematical expression.
+///
+/// The expression can contain numbers, variables, the operators
+/// `+`, `-`, `*`, `/`, `^` (or `**`), parentheses and all functions of the
+/// dual numbers (`sqrt`, `cbrt`, `exp`, `log`, `sin`, `arcsin`, `sinh`,
+/// `arcsinh`, `sph_j0`, ...). The constants `pi` and `e` are predefined.
+///
+/// Parameters
+/// ----------
+/// expr : str
+///     The expression.
+/// **variables
+///     Values of the variables as real numbers, (hyper) dual numbers or
+///     arrays thereof.
+///
+/// Returns
+/// -------
+/// The value of the expression in the type of the variables.
+///
+/// Examples
+///
+/// >>> from dualnum import evaluate, derive1
+/// >>> evaluate("a0 + a1*T + a2*exp(-b/T)", a0=1.0, a1=0.5, a2=2.0, b=0.0, T=derive1(300.0))
+/// 153 + [0.5]ε
+pub fn evaluate(py: Python, expr: &str, variables: Option<&PyDict>) -> PyResult<PyObject> {
+    let (graph, names) = parse(expr).map_err(PyErr::new::<PyValueError, _>)?;
+    let args = names
+        .iter()
+        .map(|name| {
+            variables
+                .and_then(|v| v.get_item(name.as_str()))
+                .ok_or_else(|| {
+                    PyErr::new::<PyValueError, _>(format!("no value given for variable '{}'", name))
+                })
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    PyExpression {
+        graph,
+        scalar: true,
+    }
+    .call(py, &args)
+}
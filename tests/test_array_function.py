@@ -0,0 +1,74 @@
+import numpy as np
+import pytest
+from dualnum import Dual64, DualObjectArray, derive1
+from dualnum.testing import assert_allclose
+
+
+def test_derive1_returns_ndarray():
+    x = derive1(np.array([1.0, 2.0, 3.0]))
+    assert isinstance(x, np.ndarray)
+    assert isinstance(x, DualObjectArray)
+    assert isinstance(x * x, DualObjectArray)
+    assert isinstance(derive1([1.0, 2.0]), list)
+
+
+def test_numpy_reductions_of_derive1_output():
+    x = derive1(np.array([1.0, 2.0, 3.0]))
+    np.testing.assert_allclose(np.sum(x * x).first_derivative, [2.0, 4.0, 6.0])
+    np.testing.assert_allclose(np.dot(x, x).first_derivative, [2.0, 4.0, 6.0])
+    np.testing.assert_allclose(np.prod(x).first_derivative, [6.0, 3.0, 2.0])
+    norm = np.linalg.norm(x)
+    assert_allclose(norm.value, np.sqrt(14.0))
+    np.testing.assert_allclose(norm.first_derivative, np.array([1.0, 2.0, 3.0]) / np.sqrt(14.0))
+
+
+def test_dot_shapes():
+    x = derive1(np.array([1.0, 2.0]))
+    a = np.array([[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]])
+    y = np.dot(a, x)
+    assert y.shape == (3,)
+    np.testing.assert_allclose([yi.first_derivative for yi in y], a)
+    assert np.dot(x, a.T).shape == (3,)
+    with pytest.raises(ValueError):
+        np.dot(a, np.append(x, x))
+
+
+def test_numpy_interp_of_dual_scalar():
+    xp, fp = [0.0, 1.0, 2.0], [0.0, 2.0, 3.0]
+    y = np.interp(derive1(0.5), xp, fp)
+    assert isinstance(y, Dual64)
+    assert_allclose(y, Dual64(1.0, 2.0))
+
+
+def test_numpy_interp_of_derive1_output():
+    xp, fp = [0.0, 1.0, 2.0], [0.0, 2.0, 3.0]
+    x = np.array([-1.0, 0.5, 1.5, 3.0])
+    y = np.interp(derive1(x), xp, fp)
+    assert y.shape == x.shape
+    np.testing.assert_allclose([yi.value for yi in y], np.interp(x, xp, fp))
+    np.testing.assert_allclose(
+        [yi.first_derivative for yi in y], np.diag([0.0, 2.0, 1.0, 0.0])
+    )
+
+
+def test_interp_nan():
+    xp, fp = [0.0, 1.0, 2.0], [0.0, 2.0, 3.0]
+    assert np.isnan(np.interp(Dual64(np.nan, 1.0), xp, fp).value)
+    with pytest.raises(ValueError):
+        np.interp(Dual64(0.5, 1.0), [0.0, np.nan, 2.0], fp)
+    with pytest.raises(ValueError):
+        np.interp(Dual64(0.5, 1.0), [0.0, 2.0, 1.0], fp)
+
+
+def test_numpy_polyval_of_derive1_output():
+    x = derive1(np.array([1.0, 2.0]))
+    y = np.polyval([1.0, 0.0, -1.0], x)
+    np.testing.assert_allclose([yi.value for yi in y], [0.0, 3.0])
+    np.testing.assert_allclose([yi.first_derivative for yi in y], [[2.0, 0.0], [0.0, 4.0]])
+
+
+def test_unhandled_functions_use_numpy():
+    x = derive1(np.array([1.0, 2.0]))
+    y = np.concatenate([x, x])
+    assert y.shape == (4,)
+    np.testing.assert_allclose(np.sum(x * x, axis=0).first_derivative, [2.0, 4.0])
@@ -0,0 +1,21 @@
+import numpy as np
+import pytest
+from dualnum import Dual64, DualArray64, trace
+from dualnum.testing import assert_allclose
+
+
+def test_integer_exponents():
+    x = Dual64(-2.0, 1.0)
+    assert_allclose(x ** 3, Dual64(-8.0, 12.0))
+    assert_allclose(x ** np.int64(3), Dual64(-8.0, 12.0))
+    assert_allclose(x ** -1, Dual64(-0.5, -0.25))
+
+
+@pytest.mark.parametrize("n", [2 ** 31, -(2 ** 31) - 1, np.int64(2 ** 40), 2 ** 70])
+def test_integer_exponents_out_of_range(n):
+    with pytest.raises(OverflowError):
+        Dual64(-2.0, 1.0) ** n
+    with pytest.raises(OverflowError):
+        DualArray64.derive(np.array([-2.0])) ** n
+    with pytest.raises(OverflowError):
+        trace(lambda x: x ** n, 1)
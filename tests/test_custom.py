@@ -0,0 +1,24 @@
+import math
+import pytest
+from dualnum import Dual64, Dual3Dual64, custom_function
+from dualnum.testing import assert_allclose
+
+
+def dual3dual(x):
+    return Dual3Dual64(Dual64(x, 1.0), Dual64(1.0, 0.0), Dual64(0.0, 0.0), Dual64(0.0, 0.0))
+
+
+def test_dual3dual64_requires_d4f():
+    x = 0.5
+    f = custom_function(math.exp, math.exp, math.exp, math.exp, math.exp)
+    assert_allclose(f(dual3dual(x)), dual3dual(x).exp())
+    g = custom_function(math.exp, math.exp, math.exp, math.exp)
+    with pytest.raises(ValueError):
+        g(dual3dual(x))
+
+
+def test_derivatives_in_order():
+    with pytest.raises(ValueError):
+        custom_function(math.exp, math.exp, None, math.exp)
+    with pytest.raises(ValueError):
+        custom_function(math.exp, math.exp, math.exp, None, math.exp)
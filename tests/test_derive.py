@@ -0,0 +1,14 @@
+import numpy as np
+import pytest
+from dualnum import derive1, derive2, derive3
+
+
+def test_seed_subset():
+    x = derive1(np.array([1.0, 2.0, 3.0]), seed=[True, False, True])
+    np.testing.assert_allclose([xi.first_derivative for xi in x], [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0]])
+
+
+@pytest.mark.parametrize("derive", [derive1, derive2, derive3])
+def test_empty_seed(derive):
+    with pytest.raises(ValueError, match="at least one variable"):
+        derive(np.array([1.0, 2.0]), seed=[False, False])
@@ -0,0 +1,117 @@
+import numpy as np
+import pytest
+import dualnum
+from dualnum import Dual64, HyperDual64, Dual2_64, Dual3_64
+from dualnum.testing import assert_allclose
+
+DTYPES = [
+    (dualnum.dual64_dtype, lambda x: Dual64(x, 1.0), 16),
+    (dualnum.hyperdual64_dtype, lambda x: HyperDual64(x, 1.0, 1.0, 0.0), 32),
+    (dualnum.dual2_64_dtype, lambda x: Dual2_64(x, 1.0, 0.0), 24),
+    (dualnum.dual3_64_dtype, lambda x: Dual3_64(x, 1.0, 0.0, 0.0), 32),
+]
+
+UNARY = [
+    ("negative", lambda x: -x),
+    ("reciprocal", lambda x: x.recip()),
+    ("sqrt", lambda x: x.sqrt()),
+    ("cbrt", lambda x: x.cbrt()),
+    ("exp", lambda x: x.exp()),
+    ("exp2", lambda x: x.exp2()),
+    ("expm1", lambda x: x.expm1()),
+    ("log", lambda x: x.log()),
+    ("log2", lambda x: x.log2()),
+    ("log10", lambda x: x.log10()),
+    ("log1p", lambda x: x.log1p()),
+    ("sin", lambda x: x.sin()),
+    ("cos", lambda x: x.cos()),
+    ("tan", lambda x: x.tan()),
+    ("arcsin", lambda x: x.arcsin()),
+    ("arccos", lambda x: x.arccos()),
+    ("arctan", lambda x: x.arctan()),
+    ("sinh", lambda x: x.sinh()),
+    ("cosh", lambda x: x.cosh()),
+    ("tanh", lambda x: x.tanh()),
+    ("arcsinh", lambda x: x.arcsinh()),
+    ("arccosh", lambda x: x.arccosh()),
+    ("arctanh", lambda x: x.arctanh()),
+]
+
+BINARY = [
+    ("add", lambda x, y: x + y),
+    ("subtract", lambda x, y: x - y),
+    ("multiply", lambda x, y: x * y),
+    ("true_divide", lambda x, y: x / y),
+]
+
+
+def strided(dtype, new, values):
+    """Array with every second element taken from a larger array (non-contiguous)."""
+    x = np.empty(2 * len(values), dtype=dtype)
+    for i, v in enumerate(values):
+        x[2 * i] = new(v)
+        x[2 * i + 1] = new(-1.0)
+    y = x[::2]
+    assert not y.flags["C_CONTIGUOUS"]
+    return y
+
+
+@pytest.mark.parametrize("dtype, new, itemsize", DTYPES)
+def test_registration(dtype, new, itemsize):
+    assert isinstance(dtype, np.dtype)
+    assert dtype.itemsize == itemsize
+    assert np.dtype(dtype) == dtype
+    assert issubclass(dtype.type, np.generic)
+    assert np.dtype(dtype.type) == dtype
+    assert np.zeros(3, dtype=dtype).dtype == dtype
+
+
+@pytest.mark.parametrize("dtype, new, itemsize", DTYPES)
+@pytest.mark.parametrize("float_type", [np.float64, np.float32])
+def test_cast_from_float(dtype, new, itemsize, float_type):
+    values = np.array([0.5, -1.25, 3.0], dtype=float_type)
+    x = values.astype(dtype)
+    assert x.dtype == dtype
+    # new(v) - new(0.0) has the value v and vanishing derivatives
+    expected = [new(float(v)) - new(0.0) for v in values]
+    assert_allclose(list(x), expected, rtol=0.0, atol=0.0)
+    # non-contiguous source
+    x = np.arange(6.0, dtype=float_type)[::2].astype(dtype)
+    assert [xi.value for xi in x] == [0.0, 2.0, 4.0]
+
+
+@pytest.mark.parametrize("dtype, new, itemsize", DTYPES)
+def test_round_trip(dtype, new, itemsize):
+    scalars = [new(v) for v in [0.5, 1.5, 2.5]]
+    x = np.array(scalars, dtype=dtype)
+    assert x.dtype == dtype
+    assert x.shape == (3,)
+    for xi, si in zip(x, scalars):
+        assert type(xi) is type(si)
+        assert_allclose(xi, si, rtol=0.0, atol=0.0)
+    y = np.array(list(x), dtype=dtype)
+    assert_allclose(list(y), scalars, rtol=0.0, atol=0.0)
+
+
+@pytest.mark.parametrize("dtype, new, itemsize", DTYPES)
+@pytest.mark.parametrize("name, f", UNARY)
+def test_unary_ufunc_strided(dtype, new, itemsize, name, f):
+    values = [1.2, 1.5, 1.8] if name == "arccosh" else [0.2, 0.4, 0.6]
+    x = strided(dtype, new, values)
+    out = getattr(np, name)(x)
+    assert out.dtype == dtype
+    assert_allclose(list(out), [f(new(v)) for v in values], rtol=1e-14)
+
+
+@pytest.mark.parametrize("dtype, new, itemsize", DTYPES)
+@pytest.mark.parametrize("name, f", BINARY)
+def test_binary_ufunc_strided(dtype, new, itemsize, name, f):
+    v1, v2 = [0.5, 1.5, 2.5], [3.0, -2.0, 0.25]
+    x1, x2 = strided(dtype, new, v1), strided(dtype, new, v2)
+    expected = [f(new(a), new(b)) for a, b in zip(v1, v2)]
+    out = getattr(np, name)(x1, x2)
+    assert out.dtype == dtype
+    assert_allclose(list(out), expected, rtol=1e-14)
+    # broadcasting a single element uses a zero stride
+    out = getattr(np, name)(x1, x2[:1])
+    assert_allclose(list(out), [f(new(a), new(v2[0])) for a in v1], rtol=1e-14)
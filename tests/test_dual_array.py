@@ -0,0 +1,44 @@
+import numpy as np
+import pytest
+from dualnum import DualArray64, Dual2Array64
+
+
+def test_parts_must_have_the_same_shape():
+    with pytest.raises(ValueError):
+        DualArray64(np.zeros((2, 3)), np.zeros((3, 2)))
+    with pytest.raises(ValueError):
+        Dual2Array64(np.zeros((2, 3)), np.zeros((2, 3)), np.zeros(6))
+
+
+def test_broadcasting():
+    x = DualArray64.derive(np.linspace(1.0, 2.0, 3))
+    y = DualArray64(np.ones((2, 1)), np.full((2, 1), 2.0))
+    z = x * y
+    assert z.shape == (2, 3)
+    np.testing.assert_allclose(z.value, np.broadcast_to(x.value, (2, 3)))
+    np.testing.assert_allclose(z.first_derivative, 1.0 + 2.0 * x.value[None, :] * np.ones((2, 1)))
+    with pytest.raises(ValueError):
+        x + DualArray64.derive(np.ones(2))
+
+
+def test_operators_with_ndarrays():
+    x = DualArray64.derive(np.linspace(1.0, 2.0, 3))
+    a = np.arange(6.0).reshape(2, 3)
+    for z in [a * x, x * a, np.multiply(a, x)]:
+        assert isinstance(z, DualArray64)
+        assert z.shape == (2, 3)
+        np.testing.assert_allclose(z.first_derivative, a)
+    z = a - x
+    assert isinstance(z, DualArray64)
+    np.testing.assert_allclose(z.value, a - x.value)
+    np.testing.assert_allclose(z.first_derivative, -np.ones((2, 3)))
+
+
+def test_unary_ufuncs():
+    x = Dual2Array64(np.array([1.0, 4.0]), np.ones(2), np.zeros(2))
+    z = np.sqrt(x)
+    assert isinstance(z, Dual2Array64)
+    np.testing.assert_allclose(z.value, [1.0, 2.0])
+    np.testing.assert_allclose(z.first_derivative, [0.5, 0.25])
+    np.testing.assert_allclose(z.second_derivative, [-0.25, -1.0 / 32.0])
+    np.testing.assert_allclose((-x).value, [-1.0, -4.0])
@@ -0,0 +1,45 @@
+import numpy as np
+from dualnum import derive1, derive2
+from dualnum.linalg import det, eigh
+
+
+def test_det():
+    a = np.array([[2.0, 1.0, 0.5], [1.0, 3.0, 0.2], [0.5, 0.2, 4.0]])
+    t = derive1(1.0)
+    d = det(a * t)
+    assert np.isclose(d.value, np.linalg.det(a))
+    assert np.isclose(d.first_derivative, 3.0 * np.linalg.det(a))
+
+
+def test_det_singular_real_part():
+    t = derive1(0.0)
+    assert det([[t]]).first_derivative == 1.0
+    assert det([[t, 1.0], [0.0, 1.0]]).first_derivative == 1.0
+    t = derive2(0.0)
+    d = det([[t, 1.0, 0.0], [0.0, t, 0.0], [0.0, 0.0, 1.0]])
+    assert (d.value, d.first_derivative, d.second_derivative) == (0.0, 0.0, 2.0)
+    assert det([[1.0, 2.0], [2.0, 4.0]]) == 0.0
+
+
+def test_det_non_finite():
+    t = derive1(1.0)
+    assert np.isnan(det([[np.nan * t, 1.0], [1.0, 1.0]]).value)
+    assert not np.isfinite(det([[np.inf, 0.0], [0.0, t]]).value)
+
+
+def test_eigh_degenerate():
+    t = derive1(0.0)
+    w, _ = eigh([[1.0, t], [t, 1.0]])
+    np.testing.assert_allclose([wi.value for wi in w], [1.0, 1.0])
+    np.testing.assert_allclose([wi.first_derivative for wi in w], [-1.0, 1.0])
+
+
+def test_eigh_degenerate_block():
+    t = derive1(0.0)
+    b = np.array([[0.0, 1.0, 0.0], [1.0, 0.0, 0.5], [0.0, 0.5, 1.0]])
+    a = np.diag([1.0, 1.0, 2.0]) + t * b
+    w, v = eigh(a)
+    np.testing.assert_allclose([wi.first_derivative for wi in w], [-1.0, 1.0, 1.0])
+    residual = (a @ v - v * w).ravel()
+    np.testing.assert_allclose([r.value for r in residual], 0.0, atol=1e-12)
+    np.testing.assert_allclose([r.first_derivative for r in residual], 0.0, atol=1e-12)
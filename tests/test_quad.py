@@ -0,0 +1,31 @@
+import math
+import numpy as np
+from dualnum import derive1, derive2, quad
+
+
+def f(x):
+    return np.exp(-x * x)
+
+
+def test_derivative_with_respect_to_upper_bound():
+    t = derive2(1.3)
+    y, _ = quad(f, 0.0, t)
+    assert math.isclose(y.value, quad(f, 0.0, 1.3)[0], rel_tol=1e-12)
+    assert math.isclose(y.first_derivative, math.exp(-1.69), rel_tol=1e-12)
+    assert math.isclose(y.second_derivative, -2.6 * math.exp(-1.69), rel_tol=1e-12)
+    y, _ = quad(f, derive1(1.3), 2.0)
+    assert math.isclose(y.first_derivative, -math.exp(-1.69), rel_tol=1e-12)
+
+
+def test_dual_parameter_in_integrand():
+    a = derive1(2.0)
+    nodes = []
+
+    def g(x):
+        nodes.append(x)
+        return np.exp(-a * x)
+
+    y, _ = quad(g, 0.0, 1.0)
+    assert all(isinstance(x, float) for x in nodes)
+    assert math.isclose(y.value, (1.0 - math.exp(-2.0)) / 2.0, rel_tol=1e-12)
+    assert math.isclose(y.first_derivative, -(1.0 - 3.0 * math.exp(-2.0)) / 4.0, rel_tol=1e-12)
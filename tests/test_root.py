@@ -0,0 +1,40 @@
+import numpy as np
+from dualnum import Dual64, cubic_roots, derive2, implicit_root
+from dualnum.testing import assert_allclose
+
+
+def test_implicit_root_higher_derivatives():
+    x = implicit_root(lambda x, p: x ** 2 - p, 2.0, derive2(4.0))
+    assert_allclose(x.first_derivative, 0.25)
+    assert_allclose(x.second_derivative, -0.03125)
+
+
+def test_implicit_root_cancelling_parameters():
+    p = [Dual64(1.0, np.log(3.0)), Dual64(1.0, -np.log(2.0))]
+    x = implicit_root(lambda x, p: x - p[0] * p[1], 1.0, p)
+    assert_allclose(x, Dual64(1.0, np.log(1.5)))
+
+
+def cubic_root_derivative(z, a, b, c):
+    return -(z * z * a.first_derivative + z * b.first_derivative + c.first_derivative) / (
+        (3.0 * z + 2.0 * a.value) * z + b.value
+    )
+
+
+def test_cubic_roots_one_real_root():
+    a, b, c = Dual64(1.0, np.log(3.0)), Dual64(1.0, -np.log(2.0)), Dual64(1.0, 0.0)
+    (z,) = cubic_roots(a, b, c)
+    assert_allclose(z, Dual64(-1.0, -0.5 * np.log(6.0)))
+    assert_allclose(z.first_derivative, cubic_root_derivative(z.value, a, b, c))
+
+
+def test_cubic_roots_three_real_roots():
+    a, b, c = Dual64(-6.0, np.log(3.0)), Dual64(11.0, -np.log(2.0)), Dual64(-6.0, 0.0)
+    z = cubic_roots(a, b, c)
+    assert_allclose([zi.value for zi in z], [1.0, 2.0, 3.0])
+    for zi in z:
+        assert_allclose(zi.first_derivative, cubic_root_derivative(zi.value, a, b, c))
+
+
+def test_cubic_roots_real_coefficients():
+    assert_allclose(cubic_roots(-6.0, 11.0, -6.0), [1.0, 2.0, 3.0])
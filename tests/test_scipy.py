@@ -0,0 +1,21 @@
+import numpy as np
+import pytest
+from dualnum.scipy import hess, hessp
+
+
+def rosen(x):
+    return sum(100.0 * (x[1:] - x[:-1] ** 2) ** 2 + (1.0 - x[:-1]) ** 2)
+
+
+@pytest.mark.parametrize("n", [2, 3, 5, 6, 12])
+def test_hessp_equals_hessian_times_p(n):
+    x = np.linspace(0.5, 1.5, n)
+    p = np.cos(np.arange(n))
+    np.testing.assert_allclose(hessp(rosen)(x, p), hess(rosen)(x) @ p, rtol=1e-12, atol=1e-12)
+
+
+def test_hessp_extra_arguments():
+    x = np.array([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0])
+    p = np.ones(7)
+    f = lambda x, a: a * rosen(x)
+    np.testing.assert_allclose(hessp(f)(x, p, 2.0), 2.0 * hessp(rosen)(x, p))
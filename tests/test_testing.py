@@ -0,0 +1,20 @@
+import numpy as np
+import pytest
+from dualnum import Dual64, HyperDual64
+from dualnum.testing import assert_allclose, isclose
+
+
+def test_isclose_different_structures():
+    assert isclose(Dual64(1.0, 2.0), Dual64(1.0, 2.0 + 1e-10))
+    assert not isclose(Dual64(1.0, 0.0), HyperDual64(1.0, 0.0, 0.0, 0.0))
+    assert not isclose(Dual64(1.0, 0.0), 1.0)
+    assert not isclose(Dual64(1.0, 0.0), "1.0")
+    np.testing.assert_array_equal(
+        isclose([Dual64(1.0, 0.0), Dual64(1.0, 0.0)], [Dual64(1.0, 0.0), 1.0]),
+        [True, False],
+    )
+
+
+def test_assert_allclose_different_structures():
+    with pytest.raises(AssertionError):
+        assert_allclose(Dual64(1.0, 0.0), HyperDual64(1.0, 0.0, 0.0, 0.0))
@@ -0,0 +1,38 @@
+import json
+import re
+import numpy as np
+from dualnum import Expression, derive1, trace
+from dualnum.testing import assert_allclose
+
+
+def test_json_round_trip():
+    f = trace(lambda x, y: (x * np.exp(y) + 1.5, x ** 2.5, np.log(y)), 2)
+    g = Expression.from_json(f.to_json())
+    assert g.to_json() == f.to_json()
+    assert_allclose(g(derive1(2.0), 0.5), f(derive1(2.0), 0.5))
+
+
+def test_json_round_trip_non_finite_constants():
+    f = trace(lambda x: (x + np.inf, x + (-np.inf), x * np.nan), 1)
+    s = f.to_json()
+    constants = [n["const"] for n in json.loads(s)["nodes"] if "const" in n]
+    assert sorted(constants) == ["-inf", "inf", "nan"]
+    g = Expression.from_json(s)
+    assert_allclose(g(derive1(1.0)), f(derive1(1.0)))
+
+
+def test_to_rust_skips_unused_nodes():
+    def f(x):
+        x * 5.0
+        return x + 1.0
+
+    source = trace(f, 1).to_rust()
+    assert "5.0" not in source
+    assert "1.0" in source
+
+
+def test_to_c_derivatives_do_not_divide_by_x():
+    f = trace(lambda x: x ** 0 + x ** 1 + x.sph_j0() + x.sph_j1() + x.sph_j2(), 1)
+    body = f.to_c().split("void f")[1]
+    assert "pow(" not in body
+    assert re.search(r"/ v\d", body) is None